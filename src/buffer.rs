@@ -1,9 +1,16 @@
-use crate::{Highlight, Span};
+use crate::{
+    column,
+    injection::{InjectionLayer, Injections},
+    Change, EditKind, Error, Highlight, HighlightIndex, History, LanguageRegistry, Predicates,
+    Range, Span,
+};
 use dioxus::prelude::Scope;
 use dioxus_signals::{use_signal, Signal};
 use ropey::{Rope, RopeSlice};
 use std::mem;
-use tree_sitter_c2rust::{InputEdit, Language, Node, Parser, Point, TextProvider, Tree};
+use tree_sitter_c2rust::{
+    InputEdit, Language, Node, Parser, Point, Range as TsRange, TextProvider, Tree,
+};
 
 pub fn use_buffer<'a, T>(
     cx: Scope<T>,
@@ -13,115 +20,384 @@ pub fn use_buffer<'a, T>(
     use_signal(cx, || Buffer::new(language, make_text()))
 }
 
+/// Create a buffer, returning an error if `language` can't parse the text.
+pub fn try_use_buffer<'a, T>(
+    cx: Scope<T>,
+    language: Language,
+    make_text: impl FnOnce() -> &'a str,
+) -> Result<Signal<Buffer>, Error> {
+    cx.use_hook(|| Buffer::try_new(language, make_text()).map(Signal::new))
+        .clone()
+}
+
 pub struct Buffer {
     pub rope: Rope,
     parser: Parser,
     pub tree: Tree,
+    history: History,
+    version: usize,
+    edits: Vec<InputEdit>,
+    pending_edits: Vec<InputEdit>,
+    changed_ranges: Vec<TsRange>,
+    injections: Option<Injections>,
 }
 
 impl Buffer {
+    /// Create a new buffer.
+    ///
+    /// Panics if `language` can't parse the text, see [`Buffer::try_new`].
     pub fn new(language: Language, text: &str) -> Self {
+        Self::try_new(language, text).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Create a new buffer, returning an error if `language` can't parse the text.
+    pub fn try_new(language: Language, text: &str) -> Result<Self, Error> {
         let mut parser = Parser::new();
-        parser.set_language(language).unwrap();
-        let tree = parser.parse(text, None).unwrap();
+        parser.set_language(language)?;
+        let tree = parser.parse(text, None).ok_or(Error::Parse)?;
 
-        Self {
+        Ok(Self {
             rope: Rope::from_str(text),
             parser,
             tree,
-        }
+            history: History::default(),
+            version: 0,
+            edits: Vec::new(),
+            pending_edits: Vec::new(),
+            changed_ranges: Vec::new(),
+            injections: None,
+        })
     }
 
     pub fn tree(&self) -> &Tree {
         &self.tree
     }
 
+    /// Parse the text matched by the injection `query` with the languages in `registry`, like fenced code blocks in Markdown.
+    ///
+    /// Injected languages are highlighted along with the buffer's language and reparsed incrementally after each edit.
+    pub fn set_injections(&mut self, query: &str, registry: LanguageRegistry) -> Result<(), Error> {
+        if query.is_empty() {
+            self.injections = None;
+            return Ok(());
+        }
+
+        let mut injections = Injections::new(self.tree.language(), query, registry)?;
+        let bytes = 0..self.rope.len_bytes();
+        injections.update(&self.tree, &self.rope, [bytes]);
+        self.injections = Some(injections);
+        Ok(())
+    }
+
+    /// Syntax trees of the text written in injected languages.
+    pub fn injection_layers(&self) -> &[InjectionLayer] {
+        self.injections
+            .as_ref()
+            .map(Injections::layers)
+            .unwrap_or_default()
+    }
+
+    /// Highlight the text of `rows` written in injected languages.
+    pub fn injection_highlights(
+        &self,
+        rows: std::ops::Range<usize>,
+        predicates: &Predicates,
+    ) -> Vec<Highlight> {
+        let bytes = self.rope.line_to_byte(rows.start)..self.rope.line_to_byte(rows.end);
+        self.injections
+            .as_ref()
+            .map(|injections| injections.highlights(&self.rope, bytes, predicates))
+            .unwrap_or_default()
+    }
+
     pub fn insert(&mut self, line: usize, col: usize, text: &str) -> Tree {
-        let char_idx = self.rope.line_to_char(line) + col;
-        let idx = self.rope.char_to_byte(char_idx);
-        self.rope.insert(char_idx, text);
+        let char_idx = self.point_to_char(Point::new(line, col));
+        self.splice(char_idx..char_idx, text);
+        self.reparse()
+    }
+
+    /// Delete the text inside `range`, returning the previous syntax tree.
+    pub fn delete(&mut self, range: Range) -> Tree {
+        self.replace(range, "")
+    }
+
+    /// Replace the text inside `range` with `text`, returning the previous syntax tree.
+    pub fn replace(&mut self, range: Range, text: &str) -> Tree {
+        self.splice(self.range_to_chars(range), text);
+        self.reparse()
+    }
+
+    /// Replace each range of characters in `edits` with its text, returning the previous syntax tree.
+    ///
+    /// The ranges must be sorted and not overlap. They are applied in reverse order
+    /// so earlier ranges stay valid, and the text is only reparsed once.
+    pub fn replace_many(&mut self, edits: &[(std::ops::Range<usize>, String)]) -> Tree {
+        for (chars, text) in edits.iter().rev() {
+            self.splice(chars.clone(), text);
+        }
+        self.reparse()
+    }
+
+    /// Version of this buffer, incremented every time the text is reparsed.
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    /// Edits applied to the syntax tree since the previous version, in order.
+    pub fn edits(&self) -> &[InputEdit] {
+        &self.edits
+    }
+
+    /// Ranges of the syntax tree whose structure changed since the previous version.
+    pub fn changed_ranges(&self) -> &[TsRange] {
+        &self.changed_ranges
+    }
+
+    /// Rows that changed since the previous version, or `None` if nothing changed.
+    ///
+    /// This includes both the edited text and any rows whose syntax changed as a result.
+    pub fn changed_rows(&self) -> Option<std::ops::Range<usize>> {
+        let mut rows: Option<std::ops::Range<usize>> = None;
+        for edit in &self.edits {
+            if let Some(rows) = &mut rows {
+                let shift = |row: &mut usize| {
+                    if *row > edit.old_end_position.row {
+                        *row = *row + edit.new_end_position.row - edit.old_end_position.row;
+                    }
+                };
+                shift(&mut rows.start);
+                shift(&mut rows.end);
+            }
+            rows = Some(union_rows(
+                rows,
+                edit.start_position.row..edit.new_end_position.row + 1,
+            ));
+        }
+
+        self.changed_ranges.iter().fold(rows, |rows, range| {
+            Some(union_rows(
+                rows,
+                range.start_point.row..range.end_point.row + 1,
+            ))
+        })
+    }
+
+    /// Start a transaction of edits that are undone together.
+    pub fn begin_transaction(&mut self, kind: EditKind, selections: &[Range]) {
+        self.history.begin(kind, selections);
+    }
+
+    /// Finish the current transaction, saving the `selections` after its edits.
+    pub fn commit_transaction(&mut self, selections: &[Range]) {
+        self.history.commit(selections);
+    }
 
+    /// Prevent the next transaction from being merged into the last undo step.
+    pub fn seal_history(&mut self) {
+        self.history.seal();
+    }
+
+    /// Undo the last transaction, returning the selections from before it.
+    pub fn undo(&mut self) -> Option<Vec<Range>> {
+        let transaction = self.history.undo()?;
+        for change in transaction.changes.iter().rev() {
+            self.apply(&change.inverse());
+        }
+        self.reparse();
+        Some(transaction.selections_before)
+    }
+
+    /// Redo the last undone transaction, returning the selections from after it.
+    pub fn redo(&mut self) -> Option<Vec<Range>> {
+        let transaction = self.history.redo()?;
+        for change in &transaction.changes {
+            self.apply(change);
+        }
+        self.reparse();
+        Some(transaction.selections_after)
+    }
+
+    /// Apply a change without recording it in the history.
+    fn apply(&mut self, change: &Change) {
+        let history = mem::take(&mut self.history);
+        let end = change.start + change.removed.chars().count();
+        self.splice(change.start..end, &change.inserted);
+        self.history = history;
+    }
+
+    /// Replace the characters in `chars` with `text` and edit the syntax tree to match.
+    ///
+    /// The tree must be reparsed afterwards with [`Buffer::reparse`].
+    fn splice(&mut self, chars: std::ops::Range<usize>, text: &str) {
+        if chars.is_empty() && text.is_empty() {
+            return;
+        }
+
+        let start_byte = self.rope.char_to_byte(chars.start);
+        let old_end_byte = self.rope.char_to_byte(chars.end);
+        let start_position = self.byte_to_point(start_byte);
+        let old_end_position = self.byte_to_point(old_end_byte);
+
+        self.history.record(Change {
+            start: chars.start,
+            removed: self.rope.slice(chars.clone()).to_string(),
+            inserted: text.to_owned(),
+        });
+        self.rope.remove(chars.clone());
+        self.rope.insert(chars.start, text);
+
+        let new_end_byte = start_byte + text.len();
         let edit = InputEdit {
-            start_byte: idx,
-            old_end_byte: idx,
-            new_end_byte: idx + text.len(),
-            start_position: Point::new(line, col),
-            old_end_position: Point::new(line, col),
-            new_end_position: Point::new(
-                line + text.lines().count() - 1,
-                col + text.chars().count(),
-            ),
+            start_byte,
+            old_end_byte,
+            new_end_byte,
+            start_position,
+            old_end_position,
+            new_end_position: self.byte_to_point(new_end_byte),
         };
         self.tree.edit(&edit);
+        if let Some(injections) = &mut self.injections {
+            injections.edit(&edit);
+        }
+        self.pending_edits.push(edit);
+    }
+
+    /// Incrementally reparse the text, reusing the edited syntax tree.
+    fn reparse(&mut self) -> Tree {
+        let tree = parse(&mut self.parser, &self.rope, Some(&self.tree))
+            .expect("the parser's language is set when the buffer is created");
+        self.changed_ranges = self.tree.changed_ranges(&tree).collect();
+        if let Some(injections) = &mut self.injections {
+            let changed_ranges = self.changed_ranges.iter();
+            injections.update(
+                &tree,
+                &self.rope,
+                changed_ranges.map(|range| range.start_byte..range.end_byte),
+            );
+        }
 
-        let tree = self
-            .parser
-            .parse_with(
-                &mut |idx, _| {
-                    self.rope
-                        .get_chunk_at_byte(idx)
-                        .map(|(chunk, _, _, _)| chunk)
-                        .unwrap_or_default()
-                },
-                None, // Some(&self.tree),
-            )
-            .unwrap();
+        self.edits = mem::take(&mut self.pending_edits);
+        self.version += 1;
         mem::replace(&mut self.tree, tree)
     }
 
-    pub fn lines(&self, range: std::ops::Range<usize>, highlights: &[Highlight]) -> Vec<Vec<Span>> {
-        self.rope
-            .lines_at(range.start)
-            .take(range.end - range.start)
-            .enumerate()
-            .map(|(idx, line)| {
-                let idx = idx + range.start;
+    /// Convert a point with a character column to a character index in the rope.
+    pub fn point_to_char(&self, point: Point) -> usize {
+        if point.row >= self.rope.len_lines() {
+            return self.rope.len_chars();
+        }
+        let line_start = self.rope.line_to_char(point.row);
+        let line_len = self.rope.line(point.row).len_chars();
+        line_start + point.column.min(line_len)
+    }
+
+    /// Convert a range of points to a sorted range of character indices in the rope.
+    pub fn range_to_chars(&self, range: Range) -> std::ops::Range<usize> {
+        let range = range.sorted();
+        self.point_to_char(range.start)..self.point_to_char(range.end)
+    }
+
+    /// Convert a character index in the rope to a point with a character column.
+    pub fn char_to_point(&self, char_idx: usize) -> Point {
+        let char_idx = char_idx.min(self.rope.len_chars());
+        let row = self.rope.char_to_line(char_idx);
+        Point::new(row, char_idx - self.rope.line_to_char(row))
+    }
+
+    /// Convert a byte offset in the rope to a tree-sitter point with a byte column.
+    fn byte_to_point(&self, byte_idx: usize) -> Point {
+        byte_to_point(&self.rope, byte_idx)
+    }
+
+    /// Split each line in the range of rows `range` into spans of highlighted text.
+    ///
+    /// The spans are built in a single pass over the lines and the highlights that overlap them.
+    pub fn lines(
+        &self,
+        range: std::ops::Range<usize>,
+        highlights: &HighlightIndex,
+    ) -> Vec<Vec<Span>> {
+        let end_row = range.end.min(self.rope.len_lines());
+        let start_row = range.start.min(end_row);
+        let highlights = highlights
+            .overlapping(self.rope.line_to_byte(start_row)..self.rope.line_to_byte(end_row));
+        let mut highlight_idx = 0;
+
+        (start_row..end_row)
+            .map(|row| {
+                let line = self.rope.line(row);
+                let line_start = self.rope.line_to_byte(row);
+                let line_end = line_start + line.len_bytes();
+
+                // Highlights that continue onto the next row are kept for it.
+                while highlights
+                    .get(highlight_idx)
+                    .is_some_and(|highlight| highlight.range.end_byte <= line_start)
+                {
+                    highlight_idx += 1;
+                }
+
                 let mut spans = Vec::new();
-                let mut iter = line.chars().enumerate().peekable();
-                let mut start = 0;
-
-                while let Some((col, _)) = iter.next() {
-                    for highlight in highlights {
-                        let start_point = highlight.range.start_point;
-                        let end_point = highlight.range.end_point;
-
-                        if start_point.row <= idx
-                            && end_point.row >= idx
-                            && start_point.column <= col
-                            && end_point.column > col
-                        {
-                            let mut end = None;
-                            while let Some((next_col, _)) = iter.peek() {
-                                if start_point.column <= *next_col && end_point.column > *next_col {
-                                    iter.next();
-                                } else {
-                                    end = Some(*next_col);
-                                    break;
-                                }
-                            }
-
-                            if let Some(end) = end {
-                                if start < col {
-                                    spans.push(Span::from_text(line.slice(start..col).to_string()));
-                                }
-                                start = end;
-
-                                spans.push(Span::from_kind(
-                                    &*highlight.kind,
-                                    line.slice(col..end).to_string(),
-                                ))
-                            }
-                        }
+                let mut col = 0;
+                for highlight in highlights[highlight_idx..]
+                    .iter()
+                    .take_while(|highlight| highlight.range.start_byte < line_end)
+                {
+                    let start = column::byte_to_char(
+                        line,
+                        highlight.range.start_byte.max(line_start) - line_start,
+                    );
+                    let end = column::byte_to_char(
+                        line,
+                        highlight.range.end_byte.min(line_end) - line_start,
+                    );
+                    if col < start {
+                        spans.push(Span::from_text(line.slice(col..start).to_string()));
+                    }
+                    if col.max(start) < end {
+                        spans.push(Span::from_kind(
+                            &*highlight.name,
+                            line.slice(col.max(start)..end).to_string(),
+                        ));
+                        col = end;
                     }
                 }
 
-                spans.push(Span::from_text(line.slice(start..).to_string()));
+                spans.push(Span::from_text(line.slice(col..).to_string()));
                 spans
             })
             .collect()
     }
 }
 
+/// Parse the text of `rope`, reusing the edited `old_tree`.
+pub(crate) fn parse(parser: &mut Parser, rope: &Rope, old_tree: Option<&Tree>) -> Option<Tree> {
+    parser.parse_with(
+        &mut |byte_idx, _| {
+            rope.get_chunk_at_byte(byte_idx)
+                .map(|(chunk, chunk_byte_idx, _, _)| &chunk.as_bytes()[byte_idx - chunk_byte_idx..])
+                .unwrap_or_default()
+        },
+        old_tree,
+    )
+}
+
+/// Convert a byte offset in `rope` to a tree-sitter point with a byte column.
+pub(crate) fn byte_to_point(rope: &Rope, byte_idx: usize) -> Point {
+    let row = rope.byte_to_line(byte_idx);
+    Point::new(row, byte_idx - rope.line_to_byte(row))
+}
+
+fn union_rows(
+    rows: Option<std::ops::Range<usize>>,
+    other: std::ops::Range<usize>,
+) -> std::ops::Range<usize> {
+    match rows {
+        Some(rows) => rows.start.min(other.start)..rows.end.max(other.end),
+        None => other,
+    }
+}
+
 pub struct Iter<'a> {
     chunks: ropey::iter::Chunks<'a>,
 }
@@ -133,6 +409,7 @@ impl<'a> Iterator for Iter<'a> {
     }
 }
 
+/// Text provider for queries that reads the text of nodes from the chunks of a rope.
 pub struct RopeProvider<'a> {
     pub slice: RopeSlice<'a>,
 }
@@ -142,7 +419,7 @@ impl<'a> TextProvider<'a> for RopeProvider<'a> {
 
     fn text(&mut self, node: Node) -> Self::I {
         let len = self.slice.len_bytes();
-        let start = node.end_byte().min(len);
+        let start = node.start_byte().min(len);
         let end = node.end_byte().min(len);
         let chunks = self.slice.byte_slice(start..end).chunks();
         Iter { chunks }
@@ -0,0 +1,83 @@
+use std::{cell::RefCell, future::Future, pin::Pin, rc::Rc};
+
+/// Clipboard the editor copies text to and pastes text from.
+///
+/// Desktop apps can implement this with their system clipboard and pass it to [`Builder::clipboard`](crate::Builder::clipboard).
+pub trait Clipboard {
+    /// Write `text` to the clipboard.
+    fn write(&self, text: String);
+
+    /// Read the current text on the clipboard.
+    fn read(&self) -> Pin<Box<dyn Future<Output = Option<String>>>>;
+}
+
+/// Clipboard that only holds text copied inside the app.
+#[derive(Clone, Default)]
+pub struct MemoryClipboard {
+    text: Rc<RefCell<Option<String>>>,
+}
+
+impl Clipboard for MemoryClipboard {
+    fn write(&self, text: String) {
+        *self.text.borrow_mut() = Some(text);
+    }
+
+    fn read(&self) -> Pin<Box<dyn Future<Output = Option<String>>>> {
+        let text = self.text.borrow().clone();
+        Box::pin(async move { text })
+    }
+}
+
+/// Clipboard of the browser, accessed through `navigator.clipboard`.
+#[cfg(target_arch = "wasm32")]
+#[derive(Clone, Copy, Default)]
+pub struct WebClipboard;
+
+#[cfg(target_arch = "wasm32")]
+impl WebClipboard {
+    fn call(method: &str, args: &js_sys::Array) -> Option<js_sys::Promise> {
+        use wasm_bindgen::JsCast;
+
+        let navigator = web_sys::window()?.navigator();
+        let clipboard = js_sys::Reflect::get(&navigator, &"clipboard".into()).ok()?;
+        let function: js_sys::Function = js_sys::Reflect::get(&clipboard, &method.into())
+            .ok()?
+            .dyn_into()
+            .ok()?;
+        function.apply(&clipboard, args).ok()?.dyn_into().ok()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Clipboard for WebClipboard {
+    fn write(&self, text: String) {
+        if let Some(promise) = Self::call("writeText", &js_sys::Array::of1(&text.into())) {
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(error) = wasm_bindgen_futures::JsFuture::from(promise).await {
+                    log::warn!("Failed to write to clipboard: {:?}", error);
+                }
+            });
+        }
+    }
+
+    fn read(&self) -> Pin<Box<dyn Future<Output = Option<String>>>> {
+        let promise = Self::call("readText", &js_sys::Array::new());
+        Box::pin(async move {
+            let value = wasm_bindgen_futures::JsFuture::from(promise?).await.ok()?;
+            value.as_string()
+        })
+    }
+}
+
+/// Create the default clipboard for the current target.
+pub fn default_clipboard() -> Rc<dyn Clipboard> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        Rc::new(WebClipboard)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Rc::new(MemoryClipboard::default())
+    }
+}
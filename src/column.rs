@@ -0,0 +1,115 @@
+use ropey::RopeSlice;
+use std::borrow::Cow;
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete, UnicodeSegmentation};
+use unicode_width::UnicodeWidthStr;
+
+/// Convert a byte column of `line`, like the column of a tree-sitter point, to a character column.
+pub fn byte_to_char(line: RopeSlice, byte_column: usize) -> usize {
+    line.byte_to_char(byte_column.min(line.len_bytes()))
+}
+
+/// Convert a character column of `line` to a byte column, like the column of a tree-sitter point.
+pub fn char_to_byte(line: RopeSlice, column: usize) -> usize {
+    line.char_to_byte(column.min(line.len_chars()))
+}
+
+/// Number of cells a grapheme cluster takes up in a monospace font, like 2 for CJK characters and most emoji.
+///
+/// Combining marks don't take up any cells of their own, and neither do tabs, which expand to the next tab stop.
+pub fn grapheme_width(grapheme: &str) -> usize {
+    grapheme.width()
+}
+
+/// Convert a character column of `line` to a display column, the number of cells before it in a monospace font.
+///
+/// Tabs expand to the next multiple of `tab_width` cells.
+/// A column inside a grapheme cluster is displayed at the end of the cluster.
+pub fn char_to_display(line: RopeSlice, column: usize, tab_width: usize) -> usize {
+    let text = Cow::from(line);
+    let mut char_column = 0;
+    let mut display_column = 0;
+    for grapheme in text.graphemes(true) {
+        if char_column >= column {
+            break;
+        }
+        display_column += cell_width(grapheme, display_column, tab_width);
+        char_column += grapheme.chars().count();
+    }
+    display_column
+}
+
+/// Convert a display column of `line` to the character column of the last grapheme cluster boundary at or before it.
+///
+/// Display columns past the end of the line go to the end of the line, before its line ending.
+pub fn display_to_char(line: RopeSlice, display_column: usize, tab_width: usize) -> usize {
+    let text = Cow::from(line);
+    let mut char_column = 0;
+    let mut column = 0;
+    for grapheme in text.graphemes(true) {
+        if grapheme.starts_with(['\r', '\n']) {
+            break;
+        }
+        column += cell_width(grapheme, column, tab_width);
+        if column > display_column {
+            break;
+        }
+        char_column += grapheme.chars().count();
+    }
+    char_column
+}
+
+/// Number of cells `grapheme` takes up when it starts at `display_column`.
+fn cell_width(grapheme: &str, display_column: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = tab_width.max(1);
+        tab_width - display_column % tab_width
+    } else {
+        grapheme_width(grapheme)
+    }
+}
+
+/// Find the character index of the grapheme cluster boundary after `char_idx`.
+pub fn next_grapheme(text: RopeSlice, char_idx: usize) -> usize {
+    let byte_idx = text.char_to_byte(char_idx.min(text.len_chars()));
+    let (mut chunk, mut chunk_byte_idx, _, _) = text.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, text.len_bytes(), true);
+    loop {
+        match cursor.next_boundary(chunk, chunk_byte_idx) {
+            Ok(Some(byte_idx)) => return text.byte_to_char(byte_idx),
+            Ok(None) => return text.len_chars(),
+            Err(GraphemeIncomplete::NextChunk) => {
+                chunk_byte_idx += chunk.len();
+                chunk = text.chunk_at_byte(chunk_byte_idx).0;
+            }
+            Err(GraphemeIncomplete::PreContext(byte_idx)) => {
+                let context = text.chunk_at_byte(byte_idx - 1).0;
+                cursor.provide_context(context, byte_idx - context.len());
+            }
+            Err(_) => unreachable!("the cursor is given every chunk it asks for"),
+        }
+    }
+}
+
+/// Find the character index of the grapheme cluster boundary before `char_idx`.
+pub fn prev_grapheme(text: RopeSlice, char_idx: usize) -> usize {
+    let byte_idx = text.char_to_byte(char_idx.min(text.len_chars()));
+    let (mut chunk, mut chunk_byte_idx, _, _) = text.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, text.len_bytes(), true);
+    loop {
+        match cursor.prev_boundary(chunk, chunk_byte_idx) {
+            Ok(Some(byte_idx)) => return text.byte_to_char(byte_idx),
+            Ok(None) => return 0,
+            Err(GraphemeIncomplete::PrevChunk) => {
+                let (prev_chunk, prev_chunk_byte_idx, _, _) =
+                    text.chunk_at_byte(chunk_byte_idx - 1);
+                chunk = prev_chunk;
+                chunk_byte_idx = prev_chunk_byte_idx;
+            }
+            Err(GraphemeIncomplete::PreContext(byte_idx)) => {
+                let context = text.chunk_at_byte(byte_idx - 1).0;
+                cursor.provide_context(context, byte_idx - context.len());
+            }
+            Err(_) => unreachable!("the cursor is given every chunk it asks for"),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use super::{Motion, UseEditor};
+use std::{collections::HashMap, fmt, future::Future, pin::Pin, rc::Rc};
+
+/// Task returned by a command that needs to finish asynchronously.
+pub type CommandTask = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Name of a command that can be bound to keys, like `"undo"`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Command(Rc<str>);
+
+impl Command {
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Command {
+    fn from(name: &str) -> Self {
+        Self(name.into())
+    }
+}
+
+impl From<String> for Command {
+    fn from(name: String) -> Self {
+        Self(name.into())
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+pub(super) type Handler = Rc<dyn Fn(UseEditor) -> Option<CommandTask>>;
+
+/// Registry of the commands an editor can run.
+///
+/// The [`Default`] registry contains the built-in commands of the editor.
+#[derive(Clone)]
+pub struct Commands {
+    handlers: HashMap<Command, Handler>,
+}
+
+impl Commands {
+    /// Create a registry without any commands.
+    pub fn empty() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Register a command, replacing any existing command with the same name.
+    pub fn register(
+        &mut self,
+        command: impl Into<Command>,
+        f: impl Fn(UseEditor) + 'static,
+    ) -> &mut Self {
+        self.handlers.insert(
+            command.into(),
+            Rc::new(move |editor| {
+                f(editor);
+                None
+            }),
+        );
+        self
+    }
+
+    /// Register a command that finishes asynchronously, replacing any existing command with the same name.
+    pub fn register_async<F>(
+        &mut self,
+        command: impl Into<Command>,
+        f: impl Fn(UseEditor) -> F + 'static,
+    ) -> &mut Self
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        self.handlers.insert(
+            command.into(),
+            Rc::new(move |editor| Some(Box::pin(f(editor)) as CommandTask)),
+        );
+        self
+    }
+
+    /// Returns `true` if a command named `command` is registered.
+    pub fn contains(&self, command: &Command) -> bool {
+        self.handlers.contains_key(command)
+    }
+
+    /// Run `command` on `editor`, returning its task if it finishes asynchronously.
+    pub fn run(&self, command: &Command, editor: UseEditor) -> Option<CommandTask> {
+        self.handler(command).and_then(|handler| handler(editor))
+    }
+
+    pub(super) fn handler(&self, command: &Command) -> Option<Handler> {
+        let handler = self.handlers.get(command).cloned();
+        if handler.is_none() {
+            log::warn!("Unknown command `{}`", command);
+        }
+        handler
+    }
+}
+
+impl Default for Commands {
+    fn default() -> Self {
+        let mut commands = Self::empty();
+        commands
+            .register("undo", |editor| editor.undo())
+            .register("redo", |editor| editor.redo())
+            .register("copy", |editor| editor.copy())
+            .register("cut", |editor| editor.cut())
+            .register_async("paste", |editor| editor.paste())
+            .register("newline", |editor| editor.insert("\n"))
+            .register("indent", |editor| editor.indent())
+            .register("outdent", |editor| editor.outdent())
+            .register("delete_backward", |editor| editor.delete_backward())
+            .register("delete_forward", |editor| editor.delete_forward())
+            .register("delete_word_backward", |editor| {
+                editor.delete_word_backward()
+            })
+            .register("delete_word_forward", |editor| editor.delete_word_forward())
+            .register("add_cursor_above", |editor| editor.add_cursor_above())
+            .register("add_cursor_below", |editor| editor.add_cursor_below())
+            .register("select_next_occurrence", |editor| {
+                editor.select_next_occurrence()
+            })
+            .register("open_search", |editor| editor.open_search())
+            .register("close_search", |editor| editor.close_search())
+            .register("find_next", |editor| editor.find_next())
+            .register("find_previous", |editor| editor.find_previous())
+            .register("expand_selection", |editor| editor.expand_selection())
+            .register("shrink_selection", |editor| editor.shrink_selection())
+            .register("select_next_sibling", |editor| editor.select_next_sibling())
+            .register("select_previous_sibling", |editor| {
+                editor.select_previous_sibling()
+            });
+
+        for (name, motion) in [
+            ("left", Motion::Left),
+            ("right", Motion::Right),
+            ("up", Motion::Up),
+            ("down", Motion::Down),
+            ("word_left", Motion::WordLeft),
+            ("word_right", Motion::WordRight),
+            ("line_start", Motion::LineStart),
+            ("line_end", Motion::LineEnd),
+            ("document_start", Motion::DocumentStart),
+            ("document_end", Motion::DocumentEnd),
+            ("page_up", Motion::PageUp),
+            ("page_down", Motion::PageDown),
+        ] {
+            commands
+                .register(format!("move_{name}"), move |editor| {
+                    editor.move_cursor(motion, false)
+                })
+                .register(format!("select_{name}"), move |editor| {
+                    editor.move_cursor(motion, true)
+                });
+        }
+
+        commands
+    }
+}
@@ -1,13 +1,14 @@
 use dioxus::prelude::*;
 
 #[component]
-pub fn Cursor(cx: Scope, x: f64, y: f64, is_active: bool) -> Element {
+pub fn Cursor(cx: Scope, x: f64, y: f64, is_active: bool, color: String) -> Element {
     render!(div {
         position: "absolute",
         top: "{y}px",
         left: "{x}px",
         width: "3px",
         height: "24px",
+        background: "{color}",
         class: "cursor",
         z_index: 9,
         display: if *is_active { "block" } else { "none" }
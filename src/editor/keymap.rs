@@ -0,0 +1,248 @@
+use super::Command;
+use dioxus::html::input_data::keyboard_types::{Key, Modifiers};
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// Key pressed together with a set of modifiers, like `Ctrl+Shift+Z`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl KeyChord {
+    /// Create a new key chord, ignoring the case of character keys.
+    ///
+    /// Shift is ignored for symbols, like `+`, since it's already part of the character that's typed.
+    /// Bind the shifted symbol instead, like `Ctrl++` for `Ctrl+Shift+=` on a US keyboard.
+    pub fn new(key: Key, mut modifiers: Modifiers) -> Self {
+        let key = match key {
+            Key::Character(text) => {
+                let lowercase = text.to_lowercase();
+                let is_symbol = lowercase == text.to_uppercase() && !text.trim().is_empty();
+                if is_symbol {
+                    modifiers.remove(Modifiers::SHIFT);
+                }
+                Key::Character(lowercase)
+            }
+            key => key,
+        };
+        Self { key, modifiers }
+    }
+
+    /// Returns `true` if this chord only presses a modifier key.
+    pub fn is_modifier(&self) -> bool {
+        matches!(
+            self.key,
+            Key::Shift | Key::Control | Key::Alt | Key::Meta | Key::AltGraph | Key::Super
+        )
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = ParseKeyError;
+
+    /// Parse a chord like `Ctrl+Shift+Z` or `Alt+ArrowUp`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<_> = s.split('+').collect();
+        // Allow binding the plus key itself, like `Ctrl++`.
+        if s.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("+");
+        }
+        let (key, modifier_names) = parts
+            .split_last()
+            .filter(|(key, _)| !key.is_empty())
+            .ok_or_else(|| ParseKeyError(s.to_owned()))?;
+
+        let mut modifiers = Modifiers::empty();
+        for name in modifier_names {
+            modifiers |= match name.to_lowercase().as_str() {
+                "ctrl" | "control" => Modifiers::CONTROL,
+                "shift" => Modifiers::SHIFT,
+                "alt" | "option" => Modifiers::ALT,
+                "meta" | "cmd" | "super" => Modifiers::META,
+                _ => return Err(ParseKeyError(s.to_owned())),
+            };
+        }
+
+        let key = match *key {
+            "Space" => Key::Character(" ".to_owned()),
+            "Plus" => Key::Character("+".to_owned()),
+            key => Key::from_str(key).unwrap_or_else(|_| Key::Character(key.to_owned())),
+        };
+        Ok(Self::new(key, modifiers))
+    }
+}
+
+/// Error returned when a key binding can't be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseKeyError(pub String);
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid key binding `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseKeyError {}
+
+/// Result of looking up a sequence of key chords in a [`Keymap`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Lookup {
+    /// The sequence is bound to a command.
+    Command(Command),
+
+    /// The sequence is the start of a longer binding, like `Ctrl+K` in `Ctrl+K Ctrl+C`.
+    Pending,
+
+    /// The sequence isn't bound.
+    None,
+}
+
+/// Map of key chord sequences to commands.
+///
+/// The [`Default`] keymap contains the standard bindings of the editor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: HashMap<Vec<KeyChord>, Command>,
+}
+
+impl Keymap {
+    /// Create a keymap without any bindings.
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Bind a sequence of space-separated chords, like `Ctrl+K Ctrl+C`, to `command`.
+    pub fn bind(
+        &mut self,
+        keys: &str,
+        command: impl Into<Command>,
+    ) -> Result<&mut Self, ParseKeyError> {
+        let keys = parse_sequence(keys)?;
+        self.bindings.insert(keys, command.into());
+        Ok(self)
+    }
+
+    /// Remove the binding for a sequence of space-separated chords.
+    pub fn unbind(&mut self, keys: &str) -> Result<Option<Command>, ParseKeyError> {
+        let keys = parse_sequence(keys)?;
+        Ok(self.bindings.remove(&keys))
+    }
+
+    /// Look up the command bound to a sequence of chords.
+    pub fn lookup(&self, keys: &[KeyChord]) -> Lookup {
+        if let Some(command) = self.bindings.get(keys) {
+            return Lookup::Command(command.clone());
+        }
+
+        let is_prefix = self
+            .bindings
+            .keys()
+            .any(|binding| binding.len() > keys.len() && binding.starts_with(keys));
+        if is_prefix {
+            Lookup::Pending
+        } else {
+            Lookup::None
+        }
+    }
+
+    /// Iterate over the bindings in this keymap.
+    pub fn bindings(&self) -> impl Iterator<Item = (&[KeyChord], &Command)> {
+        self.bindings
+            .iter()
+            .map(|(keys, command)| (keys.as_slice(), command))
+    }
+}
+
+/// Keys that copy, cut and paste together with Ctrl or Meta.
+pub(crate) const CLIPBOARD_KEYS: [(&str, &str); 3] = [("C", "copy"), ("X", "cut"), ("V", "paste")];
+
+impl Default for Keymap {
+    /// The standard bindings of the editor, or [`Keymap::web`] on the web.
+    fn default() -> Self {
+        Self::standard(cfg!(target_arch = "wasm32"))
+    }
+}
+
+impl Keymap {
+    /// The standard bindings of the editor without the clipboard keys,
+    /// which are left to the browser so it sends the copy, cut and paste events with their data.
+    pub fn web() -> Self {
+        Self::standard(true)
+    }
+
+    fn standard(is_web: bool) -> Self {
+        let mut keymap = Self::empty();
+        let mut bind = |keys: &str, command: &str| {
+            keymap.bind(keys, command).expect("invalid default binding");
+        };
+
+        if !is_web {
+            for (key, command) in CLIPBOARD_KEYS {
+                bind(&format!("Ctrl+{key}"), command);
+                bind(&format!("Meta+{key}"), command);
+            }
+        }
+        for (key, command) in [
+            ("Z", "undo"),
+            ("Shift+Z", "redo"),
+            ("Y", "redo"),
+            ("D", "select_next_occurrence"),
+            ("F", "open_search"),
+            ("Home", "move_document_start"),
+            ("End", "move_document_end"),
+            ("Shift+Home", "select_document_start"),
+            ("Shift+End", "select_document_end"),
+        ] {
+            bind(&format!("Ctrl+{key}"), command);
+            bind(&format!("Meta+{key}"), command);
+        }
+
+        for modifier in ["Ctrl", "Alt"] {
+            bind(&format!("{modifier}+Backspace"), "delete_word_backward");
+            bind(&format!("{modifier}+Delete"), "delete_word_forward");
+            bind(&format!("{modifier}+ArrowLeft"), "move_word_left");
+            bind(&format!("{modifier}+ArrowRight"), "move_word_right");
+            bind(&format!("{modifier}+Shift+ArrowLeft"), "select_word_left");
+            bind(&format!("{modifier}+Shift+ArrowRight"), "select_word_right");
+        }
+
+        for (key, name) in [
+            ("ArrowLeft", "left"),
+            ("ArrowRight", "right"),
+            ("ArrowUp", "up"),
+            ("ArrowDown", "down"),
+            ("Home", "line_start"),
+            ("End", "line_end"),
+            ("PageUp", "page_up"),
+            ("PageDown", "page_down"),
+        ] {
+            bind(key, &format!("move_{name}"));
+            bind(&format!("Shift+{key}"), &format!("select_{name}"));
+        }
+
+        bind("Ctrl+Alt+ArrowUp", "add_cursor_above");
+        bind("Ctrl+Alt+ArrowDown", "add_cursor_below");
+        bind("F3", "find_next");
+        bind("Shift+F3", "find_previous");
+        bind("Escape", "close_search");
+        bind("Alt+ArrowUp", "expand_selection");
+        bind("Alt+ArrowDown", "shrink_selection");
+        bind("Alt+N", "select_next_sibling");
+        bind("Alt+P", "select_previous_sibling");
+        bind("Enter", "newline");
+        bind("Tab", "indent");
+        bind("Shift+Tab", "outdent");
+        bind("Backspace", "delete_backward");
+        bind("Delete", "delete_forward");
+
+        keymap
+    }
+}
+
+fn parse_sequence(keys: &str) -> Result<Vec<KeyChord>, ParseKeyError> {
+    keys.split_whitespace().map(KeyChord::from_str).collect()
+}
@@ -1,49 +1,119 @@
-use crate::Span;
+use crate::{Span, Theme};
 use dioxus::prelude::*;
+use dioxus_signals::Signal;
 
 #[component]
-pub fn Line(cx: Scope, spans: Vec<Span>, is_selected: bool, top: f64, height: f64) -> Element {
-    let spans = spans.iter().enumerate().map(|(span_idx, span)| {
-        render!(LineSpan {
-            key: "{span_idx}",
-            span: span.clone()
-        })
-    });
+pub fn Line(
+    cx: Scope,
+    spans: Vec<Span>,
+    is_selected: bool,
+    top: f64,
+    height: f64,
+
+    /// Indices of the characters that start a wrapped row, after the first row.
+    breaks: Vec<usize>,
+
+    /// Horizontal position of the wrapped rows.
+    indent: f64,
+    row_height: f64,
+    tab_width: usize,
+    theme: Signal<Theme>,
+) -> Element {
+    let rows = wrap_spans(spans, breaks)
+        .into_iter()
+        .enumerate()
+        .map(|(row, spans)| {
+            let top = row as f64 * row_height;
+            let left = if row == 0 { 0. } else { *indent };
+            let spans = spans.into_iter().enumerate().map(|(span_idx, span)| {
+                render!(LineSpan {
+                    key: "{span_idx}",
+                    span: span,
+                    theme: *theme
+                })
+            });
+            render!(div {
+                key: "{row}",
+                position: "absolute",
+                top: "{top}px",
+                left: "{left}px",
+                white_space: "pre",
+                tab_size: "{tab_width}",
+                spans
+            })
+        });
+    let border_color = if *is_selected {
+        theme.read().current_line.clone()
+    } else {
+        "rgba(0, 0, 0, 0)".to_owned()
+    };
 
     render!(div {
         position: "absolute",
         top: "{top}px",
         width: "100%",
         height: "{height}px",
-        white_space: "pre",
-        border: if *is_selected {
-            "2px solid #c6cdd5"
-        } else {
-            "2px solid rgba(0, 0, 0, 0)"
-        },
+        border: "2px solid {border_color}",
         box_sizing: "border-box",
-        spans
+        rows
     })
 }
 
+/// Split `spans` into the wrapped rows of a line, starting a new row at each character index in `breaks`.
+fn wrap_spans(spans: &[Span], breaks: &[usize]) -> Vec<Vec<Span>> {
+    let mut rows = vec![Vec::new()];
+    let mut breaks = breaks.iter().copied().peekable();
+    let mut span_start = 0;
+    for span in spans {
+        let len = span.text.chars().count();
+        let mut text = &*span.text;
+        let mut text_start = span_start;
+        while let Some(row_start) = breaks.next_if(|&row_start| row_start < span_start + len) {
+            let byte_idx = text
+                .char_indices()
+                .nth(row_start - text_start)
+                .map_or(text.len(), |(byte_idx, _)| byte_idx);
+            let (head, tail) = text.split_at(byte_idx);
+            if !head.is_empty() {
+                rows.last_mut()
+                    .unwrap()
+                    .push(Span::new(span.kind.clone(), head));
+            }
+            rows.push(Vec::new());
+            text = tail;
+            text_start = row_start;
+        }
+
+        let row = rows.last_mut().unwrap();
+        if text.len() == span.text.len() {
+            row.push(span.clone());
+        } else if !text.is_empty() {
+            row.push(Span::new(span.kind.clone(), text));
+        }
+        span_start += len;
+    }
+    rows
+}
+
 #[component]
-fn LineSpan(cx: Scope, span: Span) -> Element {
-    let color = match span.kind {
-        Some(ref s) => match &**s {
-            "fn" | "struct" | "pub" | "use" | "let" | "match" | "async" | "unsafe" | "move"
-            | "|" | "impl" | "mutable_specifier" | "self" => "rgb(207, 34, 46)",
-            "attribute_item" | "identifier" | "type_identifier" | "!" | "'" => "rgb(96, 59, 179)",
-            "primitive_type" | "boolean_identifier" | "::" | "*" => "rgb(5, 80, 174)",
-            "string_literal" | "integer_literal" => "rgb(7, 69, 124)",
-            "{" | "}" => "#076678",
-            "(" | ")" | "=>" | "&" => "#faa356",
-            ";" | "," | "<" | ">" | ":" => "#ccc",
-            _ => "#000",
-        },
-        _ => "#000",
-    };
+fn LineSpan(cx: Scope, span: Span, theme: Signal<Theme>) -> Element {
+    let theme = theme.read();
+    let style = span.kind.as_deref().and_then(|kind| theme.style(kind));
+    let color = style
+        .and_then(|style| style.color.as_deref())
+        .unwrap_or(&theme.foreground);
+    let is_bold = style.is_some_and(|style| style.is_bold);
+    let is_italic = style.is_some_and(|style| style.is_italic);
+    let is_underline = style.is_some_and(|style| style.is_underline);
 
     render!(
-        span { color: color, "data-kind": "{span.kind.as_deref().unwrap_or(\"\")}", "{span.text}" }
+        span {
+            color: "{color}",
+            font_weight: if is_bold { "bold" } else { "normal" },
+            font_style: if is_italic { "italic" } else { "normal" },
+            text_decoration: if is_underline { "underline" } else { "none" },
+            "data-kind": "{span.kind.as_deref().unwrap_or(\"\")}",
+            "{span.text}"
+        }
     )
 }
@@ -1,118 +1,187 @@
-use crate::{editor::cursor::Cursor, layout::Layout, Range};
-use dioxus::{html::input_data::keyboard_types::Key, prelude::*};
+use crate::{
+    editor::{cursor::Cursor, search_panel::SearchPanel, selection_rect::SelectionRect},
+    Range,
+};
+use dioxus::{html::input_data::keyboard_types::Modifiers, prelude::*};
 use dioxus_signals::{use_signal, Signal};
 use std::rc::Rc;
-use tree_sitter_c2rust::Point;
+
+mod command;
+pub use command::{Command, CommandTask, Commands};
 
 mod cursor;
 
+mod keymap;
+pub use keymap::{KeyChord, Keymap, Lookup, ParseKeyError};
+
 mod line;
 use line::Line;
 
+mod motion;
+pub use motion::Motion;
+
+mod selection;
+
+mod search_panel;
+
+mod selection_rect;
+
+pub mod structure;
+
 mod use_editor;
 pub use use_editor::{Builder, UseEditor};
 
+mod vim;
+pub use vim::{Register, UseVim, VimAction, VimMode, VimParse};
+
+/// Width of the line number gutter, which is also the margin between it and the text.
+const GUTTER_WIDTH: f64 = 50.;
+
 /// Text editor
 #[component]
-pub fn Editor(
-    cx: Scope,
-    editor: UseEditor,
-
-    /// Font size of the editor text.
-    #[props(default = 14.)]
-    font_size: f64,
-) -> Element {
-    to_owned![editor, font_size];
+pub fn Editor(cx: Scope, editor: UseEditor) -> Element {
+    to_owned![editor];
     let line_height = editor.line_height;
+    let font_size = editor.font_size;
 
     let lines_ref: Signal<Option<Rc<MountedData>>> = use_signal(cx, || None);
 
-    let layout = use_signal(cx, || Layout::new(font_size, line_height));
+    let layout = editor.layout;
+    // Text is measured with a canvas on the web, where the font is only known to the browser.
+    #[cfg(target_arch = "wasm32")]
+    cx.use_hook(|| match crate::layout::Canvas::try_new(font_size) {
+        Ok(canvas) => {
+            let measurer: Box<dyn crate::layout::TextMeasurer> = Box::new(canvas);
+            layout.set(crate::layout::Layout::new(measurer, line_height));
+        }
+        Err(error) => editor.error.set(Some(error)),
+    });
     let buffer = editor.buffer;
-    dioxus_signals::use_effect(cx, move || layout.write().measure(buffer().rope.lines()));
-
-    let layout_ref = layout();
-    let top_line = editor.list.scroll_range.start();
-
-    let is_mouse_down = use_signal(cx, || false);
-
-    let line_values = use_signal(cx, Vec::new);
     let container_size = editor.container_size;
+    let soft_wrap = editor.soft_wrap;
+    let tab_width = editor.tab_width;
+    let line_values = use_signal(cx, Vec::new);
     let scroll = editor.list.scroll_range.scroll;
-    let values = editor.list.lazy.values;
+    let visible_rows = editor.visible_rows;
+    let highlights = editor.highlights;
+    let editor_height = editor.height;
     dioxus_signals::use_effect(cx, move || {
-        let layout_ref = layout();
-        let top_line = layout_ref.line(*scroll() as _).unwrap_or_default();
-        let _bottom_line = top_line
-            + (container_size()
+        let wrap_width = if *soft_wrap() {
+            container_size()
+                .as_ref()
+                .map(|rect| rect.width() - 2. * GUTTER_WIDTH)
+        } else {
+            None
+        };
+        let top = *scroll() as f64;
+        let bottom = top
+            + container_size()
                 .as_ref()
-                .map(|rect| rect.height())
-                .unwrap_or_default()
-                / line_height)
-                .floor() as usize
-            + 1;
-
-        let values_ref = values();
-        let values = values_ref
-            .iter()
-            .cloned()
-            .zip(layout_ref.lines().iter().cloned())
-            .enumerate()
+                .map_or(editor_height, |rect| rect.height());
+
+        // Only the edited lines and the lines that become visible are measured.
+        let buffer = buffer();
+        let mut layout = layout.write();
+        layout.set_tab_width(tab_width);
+        layout.set_wrap_width(wrap_width);
+        layout.update(&buffer);
+        let rows = layout.measure_visible(buffer.rope.slice(..), top, bottom);
+        if *visible_rows.read() != rows {
+            visible_rows.set(rows.clone());
+        }
+
+        let spans = buffer.lines(rows.clone(), &highlights());
+        let values = rows
+            .zip(spans)
+            .filter_map(|(row, spans)| {
+                let line = layout.get(row)?.clone();
+                Some((row, (spans, layout.y(row), line)))
+            })
             .collect();
         line_values.set(values)
     });
 
+    let layout_ref = layout();
+    let theme = editor.theme.read();
+
+    let is_mouse_down = use_signal(cx, || false);
+
     let mut line_numbers = Vec::new();
     let mut lines = Vec::new();
-    let mut y = top_line as f64 * line_height;
-    for (line_idx, (spans, line)) in line_values {
-        let top = y;
-        y += line.height;
+    for (n, (spans, top, line)) in line_values {
+        let is_selected = editor.is_focused()
+            && editor
+                .selections
+                .read()
+                .iter()
+                .any(|selection| selection.head().row == n);
 
-        let n = line_idx + top_line;
-
-        let is_selected = if let Some(selection) = editor.selections.read().first() {
-            editor.is_focused() && n == selection.start.row
+        let line_number_color = if is_selected {
+            &theme.current_line_number
         } else {
-            false
+            &theme.line_number
         };
-
         let line_number = render!(
-            div { position: "absolute", top: "{top}px", right: 0, color: if is_selected { "#000" } else { "#888" }, line_height: "inherit", "{n + 1}" }
+            div { position: "absolute", top: "{top}px", right: 0, color: "{line_number_color}", line_height: "inherit", "{n + 1}" }
         );
         line_numbers.push(line_number);
 
         let line = render!(Line {
-            key: "{line_idx}",
+            key: "{n}",
             spans: spans,
             top: top,
             height: line.height,
-            is_selected: is_selected
+            breaks: line.breaks,
+            indent: line.indent,
+            row_height: line_height,
+            tab_width: editor.tab_width,
+            is_selected: is_selected,
+            theme: editor.theme
         });
         lines.push(line);
     }
 
-    let height = editor.buffer().rope.len_lines() as f64 * line_height;
-    let onkeydown = move |event: KeyboardEvent| match event.key() {
-        Key::Character(text) => editor.insert(&text),
-        Key::Enter => editor.insert("\n"),
-
-        _ => {}
+    let height = layout_ref.height();
+    let onkeydown = move |event: KeyboardEvent| {
+        if let Some(task) = editor.handle_key(event.key(), event.modifiers()) {
+            cx.spawn(task);
+        }
     };
     let onscroll = move |_| editor.list.scroll();
 
+    // Clipboard events are handled with the data they transfer on the web, see `listen_to_clipboard`.
+    #[cfg(not(target_arch = "wasm32"))]
+    let (oncopy, oncut, onpaste) = (
+        move |_| editor.copy(),
+        move |_| editor.cut(),
+        move |_| editor.paste(),
+    );
+    #[cfg(target_arch = "wasm32")]
+    let (oncopy, oncut, onpaste) = (|_| {}, |_| {}, |_| {});
+
+    // On the web, key presses are prevented by `listen_to_clipboard` instead,
+    // which lets the clipboard keys through so the browser sends its clipboard events.
+    let prevent_default = if cfg!(target_arch = "wasm32") {
+        ""
+    } else {
+        "onkeydown"
+    };
+
     let editor_clone = editor.clone();
     let onmousemove = move |event: MouseEvent| async move {
         if *is_mouse_down() {
+            let Some(lines_elem) = lines_ref.read().clone() else {
+                return;
+            };
+            let Ok(bounds) = lines_elem.get_client_rect().await else {
+                return;
+            };
             if let Some(selection) = editor_clone.selections.write().last_mut() {
-                let lines_elem = lines_ref.unwrap();
-                let bounds = lines_elem.get_client_rect().await.unwrap();
-                if let Some((line, col_cell)) = layout().target(
+                if let Some(point) = layout().target(
                     event.client_coordinates().x - bounds.origin.x,
                     event.client_coordinates().y - bounds.origin.y,
                 ) {
-                    let col = col_cell.unwrap_or_default();
-                    selection.end = Point::new(line, col);
+                    selection.end = point;
                 }
             }
         }
@@ -120,13 +189,54 @@ pub fn Editor(
 
     let mounted = editor.list.mounted;
     let editor_ref = editor.selections.read();
-    let cursors = editor_ref.iter().map(|selection| {
-        let [x, y] = layout_ref.pos(selection.start).unwrap_or_default();
+    let visible_rows = editor.visible_rows.read().clone();
+    let selection_rects = editor_ref
+        .iter()
+        .filter(|selection| !selection.is_empty())
+        .flat_map(|selection| layout_ref.rects(*selection, visible_rows.clone()))
+        .map(|[x, y, width, height]| {
+            render!(SelectionRect {
+                x: x,
+                y: y,
+                width: width,
+                height: height,
+                background: theme.selection.clone()
+            })
+        });
+    let search_matches = editor.search_matches.read();
+    let match_rects = search_matches
+        .iter()
+        .filter(|range| range.end.row >= visible_rows.start && range.start.row < visible_rows.end)
+        .flat_map(|range| layout_ref.rects(*range, visible_rows.clone()))
+        .map(|[x, y, width, height]| {
+            render!(SelectionRect {
+                x: x,
+                y: y,
+                width: width,
+                height: height,
+                background: theme.search_match.clone()
+            })
+        });
+    let cursors = editor_ref.iter().filter_map(|selection| {
+        let [x, y] = layout_ref.pos(selection.head())?;
 
         render!(Cursor {
             x: x,
             y: y,
-            is_active: editor.is_focused()
+            is_active: editor.is_focused(),
+            color: theme.cursor.clone()
+        })
+    });
+
+    let error_ref = editor.error.read();
+    let error_banner = error_ref.as_ref().and_then(|error| {
+        render!(div {
+            padding: "4px 8px",
+            color: "{theme.error}",
+            background: "{theme.error_background}",
+            border: "1px solid {theme.error_border}",
+            font: "13px sans-serif",
+            "{error}"
         })
     });
 
@@ -134,64 +244,177 @@ pub fn Editor(
     render!(
         div {
             position: "relative",
-            display: "flex",
-            flex_direction: "row",
             width: "800px",
-            height: "{editor.height}px",
             margin: "50px auto",
-            font: "{font_size}px monospace",
-            line_height: "26px",
-            border: "2px solid #ccc",
-            overflow: "auto",
-            tabindex: 0,
-            outline: "none",
-            user_select: "none",
-            webkit_user_select: "none",
-            prevent_default: "onkeydown",
-            onmounted: move |event| editor.list.mounted.onmounted(event),
-            onclick: move |_| {
-                editor.focus();
-                async move {
-                    let mounted = mounted.signal.read().clone();
-                    if let Some(mounted) = mounted {
-                        mounted.set_focus(true).await.unwrap();
-                    }
-                }
-            },
-            onfocusin: move |_| editor.focus(),
-            onfocusout: move |_| editor.blur(),
-            onkeydown: onkeydown,
-            onscroll: onscroll,
-            onmousemove: onmousemove,
-            onmouseup: move |_| is_mouse_down.set(false),
-            div { position: "relative", width: "50px", line_numbers.into_iter() }
+            error_banner,
+            SearchPanel { editor: editor }
             div {
-                flex: 1,
                 position: "relative",
-                margin_left: "50px",
-                height: "{height}px",
-                cursor: "text",
-                onmounted: move |event| lines_ref.set(Some(event.data)),
-                onmousedown: move |event| async move {
-                    is_mouse_down.set(true);
-                    let lines_elem = lines_ref.unwrap();
-                    let bounds = lines_elem.get_client_rect().await.unwrap();
-                    if let Some((line, col_cell))
-                        = layout()
-                            .target(
-                                event.client_coordinates().x - bounds.origin.x,
-                                event.client_coordinates().y - bounds.origin.y,
-                            )
-                    {
-                        let col = col_cell.unwrap_or_default();
-                        let mut selections = editor_clone.selections.write();
-                        selections.clear();
-                        selections.push(Range::new(Point::new(line, col), Point::new(line, col)));
+                display: "flex",
+                flex_direction: "row",
+                height: "{editor.height}px",
+                font: "{font_size}px monospace",
+                line_height: "26px",
+                color: "{theme.foreground}",
+                background: "{theme.background}",
+                border: "2px solid {theme.border}",
+                overflow: "auto",
+                tabindex: 0,
+                outline: "none",
+                user_select: "none",
+                webkit_user_select: "none",
+                prevent_default: "{prevent_default}",
+                onmounted: move |event| {
+                    #[cfg(target_arch = "wasm32")]
+                    listen_to_clipboard(&event.data, editor);
+                    editor.list.mounted.onmounted(event)
+                },
+                onclick: move |_| {
+                    editor.focus();
+                    async move {
+                        let mounted = mounted.signal.read().clone();
+                        if let Some(mounted) = mounted {
+                            if let Err(error) = mounted.set_focus(true).await {
+                                log::warn!("Failed to focus editor: {:?}", error);
+                            }
+                        }
                     }
                 },
-                cursors,
-                lines.into_iter()
+                onfocusin: move |_| editor.focus(),
+                onfocusout: move |_| editor.blur(),
+                onkeydown: onkeydown,
+                oncopy: oncopy,
+                oncut: oncut,
+                onpaste: onpaste,
+                onscroll: onscroll,
+                onmousemove: onmousemove,
+                onmouseup: move |_| is_mouse_down.set(false),
+                div { position: "relative", width: "{GUTTER_WIDTH}px", background: "{theme.gutter}", line_numbers.into_iter() }
+                div {
+                    flex: 1,
+                    position: "relative",
+                    margin_left: "{GUTTER_WIDTH}px",
+                    height: "{height}px",
+                    cursor: "text",
+                    onmounted: move |event| lines_ref.set(Some(event.data)),
+                    onmousedown: move |event| async move {
+                        is_mouse_down.set(true);
+                        let Some(lines_elem) = lines_ref.read().clone() else {
+                            return;
+                        };
+                        let Ok(bounds) = lines_elem.get_client_rect().await else {
+                            return;
+                        };
+                        if let Some(point)
+                            = layout()
+                                .target(
+                                    event.client_coordinates().x - bounds.origin.x,
+                                    event.client_coordinates().y - bounds.origin.y,
+                                )
+                        {
+                            if event.modifiers().contains(Modifiers::ALT) {
+                                editor_clone.add_cursor(point);
+                            } else {
+                                editor_clone.selections.set(vec![Range::point(point)]);
+                            }
+                        }
+                    },
+                    match_rects,
+                    selection_rects,
+                    cursors,
+                    lines.into_iter()
+                }
             }
         }
     )
 }
+
+/// Copy, cut and paste with the data transferred by the clipboard events of the editor's element,
+/// which doesn't need permission to read the clipboard like `navigator.clipboard` does.
+///
+/// This also prevents the default of every other key press on the element.
+#[cfg(target_arch = "wasm32")]
+fn listen_to_clipboard(mounted: &MountedData, editor: UseEditor) {
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use web_sys::{DataTransfer, Event};
+
+    let Some(element) = mounted
+        .get_raw_element()
+        .ok()
+        .and_then(|raw| raw.downcast_ref::<web_sys::Element>())
+    else {
+        return;
+    };
+
+    // `ClipboardEvent` is unstable in `web-sys`, so its data is read with reflection.
+    fn clipboard_data(event: &Event) -> Option<DataTransfer> {
+        js_sys::Reflect::get(event, &"clipboardData".into())
+            .ok()?
+            .dyn_into()
+            .ok()
+    }
+
+    // Prevent the browser from handling key presses, except for the clipboard keys the keymap leaves to it.
+    let is_clipboard_key = |event: &web_sys::KeyboardEvent| {
+        (event.ctrl_key() || event.meta_key())
+            && keymap::CLIPBOARD_KEYS
+                .iter()
+                .any(|(key, _)| key.eq_ignore_ascii_case(&event.key()))
+    };
+    let onkeydown = Closure::<dyn Fn(Event)>::new(move |event: Event| {
+        let is_clipboard = event
+            .dyn_ref::<web_sys::KeyboardEvent>()
+            .is_some_and(is_clipboard_key);
+        if !is_clipboard {
+            event.prevent_default();
+        }
+    });
+
+    // Copying nothing keeps the text on the clipboard, and cutting only deletes text that was copied.
+    let copy = move |event: &Event| {
+        let text = editor.selected_text();
+        let is_copied = !text.is_empty()
+            && clipboard_data(event).is_some_and(|data| data.set_data("text/plain", &text).is_ok());
+        if is_copied {
+            event.prevent_default();
+        }
+        is_copied
+    };
+    let listeners = [
+        ("keydown", onkeydown),
+        (
+            "copy",
+            Closure::<dyn Fn(Event)>::new(move |event: Event| {
+                copy(&event);
+            }),
+        ),
+        (
+            "cut",
+            Closure::<dyn Fn(Event)>::new(move |event: Event| {
+                if copy(&event) {
+                    editor.delete_selected_text();
+                }
+            }),
+        ),
+        (
+            "paste",
+            Closure::<dyn Fn(Event)>::new(move |event: Event| {
+                let text = clipboard_data(&event).and_then(|data| data.get_data("text/plain").ok());
+                if let Some(text) = text {
+                    event.prevent_default();
+                    editor.paste_text(&text);
+                }
+            }),
+        ),
+    ];
+
+    for (name, listener) in listeners {
+        if let Err(error) =
+            element.add_event_listener_with_callback(name, listener.as_ref().unchecked_ref())
+        {
+            log::warn!("Failed to listen to {} events: {:?}", name, error);
+        }
+        // The listeners live as long as the element they're added to.
+        listener.forget();
+    }
+}
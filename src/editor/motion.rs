@@ -0,0 +1,165 @@
+use crate::{column, Buffer};
+use ropey::Rope;
+use tree_sitter_c2rust::Point;
+
+/// Class of a character used to find word boundaries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+impl CharClass {
+    pub fn of(c: char) -> Self {
+        if c.is_whitespace() {
+            Self::Whitespace
+        } else if c.is_alphanumeric() || c == '_' {
+            Self::Word
+        } else {
+            Self::Punctuation
+        }
+    }
+}
+
+/// Find the start of the word before `char_idx`, skipping any whitespace in between.
+pub fn prev_word_boundary(rope: &Rope, mut char_idx: usize) -> usize {
+    while char_idx > 0 && CharClass::of(rope.char(char_idx - 1)) == CharClass::Whitespace {
+        char_idx -= 1;
+    }
+
+    if char_idx > 0 {
+        let class = CharClass::of(rope.char(char_idx - 1));
+        while char_idx > 0 && CharClass::of(rope.char(char_idx - 1)) == class {
+            char_idx -= 1;
+        }
+    }
+    char_idx
+}
+
+/// Find the end of the word after `char_idx`, skipping any whitespace in between.
+pub fn next_word_boundary(rope: &Rope, mut char_idx: usize) -> usize {
+    let len = rope.len_chars();
+    while char_idx < len && CharClass::of(rope.char(char_idx)) == CharClass::Whitespace {
+        char_idx += 1;
+    }
+
+    if char_idx < len {
+        let class = CharClass::of(rope.char(char_idx));
+        while char_idx < len && CharClass::of(rope.char(char_idx)) == class {
+            char_idx += 1;
+        }
+    }
+    char_idx
+}
+
+/// Find the range of word characters around `char_idx`.
+pub fn word_at(rope: &Rope, char_idx: usize) -> std::ops::Range<usize> {
+    let is_word = |idx| CharClass::of(rope.char(idx)) == CharClass::Word;
+
+    let mut start = char_idx;
+    while start > 0 && is_word(start - 1) {
+        start -= 1;
+    }
+    let mut end = char_idx;
+    while end < rope.len_chars() && is_word(end) {
+        end += 1;
+    }
+    start..end
+}
+
+/// Movement of a cursor through a buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    DocumentStart,
+    DocumentEnd,
+    PageUp,
+    PageDown,
+}
+
+impl Motion {
+    /// Move the cursor at `point`, returning its new position and goal column.
+    ///
+    /// Vertical motions try to stay on the `goal_column` of the previous vertical motion,
+    /// which is a display column so the cursor keeps its place on screen across tabs and wide characters.
+    pub fn apply(
+        self,
+        buffer: &Buffer,
+        point: Point,
+        goal_column: Option<usize>,
+        page_rows: usize,
+        tab_width: usize,
+    ) -> (Point, Option<usize>) {
+        let rope = &buffer.rope;
+        let char_idx = buffer.point_to_char(point);
+        let goal = goal_column.unwrap_or_else(|| {
+            column::char_to_display(rope.line(point.row), point.column, tab_width)
+        });
+        let vertical = |row| {
+            let column = column::display_to_char(rope.line(row), goal, tab_width);
+            (Point::new(row, column), Some(goal))
+        };
+
+        let point = match self {
+            Self::Left => buffer.char_to_point(column::prev_grapheme(rope.slice(..), char_idx)),
+            Self::Right => buffer.char_to_point(column::next_grapheme(rope.slice(..), char_idx)),
+            Self::Up if point.row == 0 => Point::new(0, 0),
+            Self::Up => return vertical(point.row - 1),
+            Self::Down if point.row + 1 >= rope.len_lines() => {
+                Point::new(point.row, line_len(rope, point.row))
+            }
+            Self::Down => return vertical(point.row + 1),
+            Self::WordLeft => buffer.char_to_point(prev_word_boundary(rope, char_idx)),
+            Self::WordRight => buffer.char_to_point(next_word_boundary(rope, char_idx)),
+            Self::LineStart => {
+                let indent = rope
+                    .line(point.row)
+                    .chars()
+                    .take_while(|c| *c == ' ' || *c == '\t')
+                    .count();
+                let column = if point.column == indent { 0 } else { indent };
+                Point::new(point.row, column)
+            }
+            Self::LineEnd => Point::new(point.row, line_len(rope, point.row)),
+            Self::DocumentStart => Point::new(0, 0),
+            Self::DocumentEnd => buffer.char_to_point(rope.len_chars()),
+            Self::PageUp => {
+                let row = point.row.saturating_sub(page_rows);
+                return vertical(row);
+            }
+            Self::PageDown => {
+                let row = (point.row + page_rows).min(rope.len_lines().saturating_sub(1));
+                return vertical(row);
+            }
+        };
+        (point, None)
+    }
+
+    /// Returns `true` if this motion collapses a selection to its start instead of moving.
+    pub fn is_backward(self) -> bool {
+        matches!(self, Self::Left | Self::WordLeft)
+    }
+
+    /// Returns `true` if this motion collapses a selection to its end instead of moving.
+    pub fn is_forward(self) -> bool {
+        matches!(self, Self::Right | Self::WordRight)
+    }
+}
+
+/// Length of the line at `row` in characters, excluding its line ending.
+pub fn line_len(rope: &Rope, row: usize) -> usize {
+    let line = rope.line(row);
+    let mut len = line.len_chars();
+    while len > 0 && matches!(line.char(len - 1), '\n' | '\r') {
+        len -= 1;
+    }
+    len
+}
@@ -0,0 +1,155 @@
+use crate::{SearchMode, SearchQuery, Theme, UseEditor};
+use dioxus::{
+    html::input_data::keyboard_types::{Key, Modifiers},
+    prelude::*,
+};
+use dioxus_signals::{use_signal, Signal};
+
+/// Panel to search and replace the text of an editor.
+#[component]
+pub fn SearchPanel(cx: Scope, editor: UseEditor) -> Element {
+    let editor = *editor;
+    let replacement = use_signal(cx, String::new);
+
+    if !editor.is_search_open() {
+        return None;
+    }
+
+    let theme = editor.theme.read();
+    let query = editor.search_query.read().clone();
+    let matches = editor.search_matches.read();
+    let current = editor.selections.read().last().and_then(|selection| {
+        matches
+            .iter()
+            .position(|range| range.sorted() == selection.sorted())
+    });
+    let status = match (&*editor.search_error.read(), current) {
+        (Some(error), _) => error.to_string(),
+        (None, Some(idx)) => format!("{} of {}", idx + 1, matches.len()),
+        (None, None) => format!("{} results", matches.len()),
+    };
+    drop(matches);
+
+    let update = move |f: fn(&mut SearchQuery)| {
+        let mut query = editor.search_query.read().clone();
+        f(&mut query);
+        editor.set_search_query(query);
+    };
+    let onkeydown = move |event: KeyboardEvent| {
+        event.stop_propagation();
+        match event.key() {
+            Key::Enter if event.modifiers().contains(Modifiers::SHIFT) => editor.find_previous(),
+            Key::Enter => editor.find_next(),
+            Key::Escape => editor.close_search(),
+            _ => {}
+        }
+    };
+
+    render!(
+        div {
+            position: "absolute",
+            top: "4px",
+            right: "20px",
+            z_index: 10,
+            display: "flex",
+            flex_direction: "column",
+            gap: "4px",
+            padding: "6px",
+            background: "{theme.panel}",
+            border: "1px solid {theme.panel_border}",
+            border_radius: "4px",
+            font: "13px sans-serif",
+            div { display: "flex", gap: "4px", align_items: "center",
+                input {
+                    placeholder: if query.mode == SearchMode::Query { "(call_expression) @call" } else { "Find" },
+                    value: "{query.pattern}",
+                    autofocus: true,
+                    oninput: move |event| {
+                        let mut query = editor.search_query.read().clone();
+                        query.pattern = event.value.clone();
+                        editor.set_search_query(query);
+                    },
+                    onkeydown: onkeydown
+                }
+                ToggleButton {
+                    theme: editor.theme,
+                    label: "Aa",
+                    title: "Match case",
+                    is_active: query.is_case_sensitive,
+                    onclick: move |_| update(|query| query.is_case_sensitive = !query.is_case_sensitive)
+                }
+                ToggleButton {
+                    theme: editor.theme,
+                    label: "W",
+                    title: "Match whole word",
+                    is_active: query.is_whole_word,
+                    onclick: move |_| update(|query| query.is_whole_word = !query.is_whole_word)
+                }
+                ToggleButton {
+                    theme: editor.theme,
+                    label: ".*",
+                    title: "Use regular expression",
+                    is_active: query.mode == SearchMode::Regex,
+                    onclick: move |_| update(|query| toggle_mode(query, SearchMode::Regex))
+                }
+                ToggleButton {
+                    theme: editor.theme,
+                    label: "()",
+                    title: "Use tree-sitter query",
+                    is_active: query.mode == SearchMode::Query,
+                    onclick: move |_| update(|query| toggle_mode(query, SearchMode::Query))
+                }
+                span { min_width: "80px", "{status}" }
+                button { title: "Previous match", onclick: move |_| editor.find_previous(), "↑" }
+                button { title: "Next match", onclick: move |_| editor.find_next(), "↓" }
+                button { title: "Close", onclick: move |_| editor.close_search(), "×" }
+            }
+            div { display: "flex", gap: "4px", align_items: "center",
+                input {
+                    placeholder: "Replace",
+                    value: "{replacement}",
+                    oninput: move |event| replacement.set(event.value.clone()),
+                    onkeydown: move |event: KeyboardEvent| {
+                        event.stop_propagation();
+                        match event.key() {
+                            Key::Enter => editor.replace(&replacement.read()),
+                            Key::Escape => editor.close_search(),
+                            _ => {}
+                        }
+                    }
+                }
+                button { onclick: move |_| editor.replace(&replacement.read()), "Replace" }
+                button { onclick: move |_| editor.replace_all(&replacement.read()), "Replace all" }
+            }
+        }
+    )
+}
+
+/// Switch `query` to `mode`, or back to a literal search if it's already using it.
+fn toggle_mode(query: &mut SearchQuery, mode: SearchMode) {
+    query.mode = if query.mode == mode {
+        SearchMode::Literal
+    } else {
+        mode
+    };
+}
+
+#[component]
+fn ToggleButton<'a>(
+    cx: Scope<'a>,
+    theme: Signal<Theme>,
+    label: &'static str,
+    title: &'static str,
+    is_active: bool,
+    onclick: EventHandler<'a, MouseEvent>,
+) -> Element<'a> {
+    let theme = theme.read();
+    render!(button {
+        title: "{title}",
+        background: if *is_active { theme.panel_active.as_str() } else { "transparent" },
+        border: "1px solid {theme.panel_border}",
+        border_radius: "3px",
+        onclick: move |event| onclick.call(event),
+        "{label}"
+    })
+}
@@ -0,0 +1,94 @@
+use crate::{Buffer, Range};
+
+/// Replacement of a range of characters in a buffer.
+pub type CharEdit = (std::ops::Range<usize>, String);
+
+/// Sort `edits` by position and clip any overlapping ranges so they can be applied together.
+pub fn normalize_edits(mut edits: Vec<CharEdit>) -> Vec<CharEdit> {
+    edits.sort_by_key(|(chars, _)| chars.start);
+
+    let mut end = 0;
+    edits.retain_mut(|(chars, text)| {
+        chars.start = chars.start.max(end);
+        chars.end = chars.end.max(chars.start);
+        end = chars.end;
+        !(chars.start == chars.end && text.is_empty())
+    });
+    edits
+}
+
+/// Map a character index from before `edits` were applied to after.
+///
+/// Indices inside or at the start of an edited range move to the end of its new text.
+pub fn map_char(char_idx: usize, edits: &[CharEdit]) -> usize {
+    let mut delta = 0isize;
+    for (chars, text) in edits {
+        let len = text.chars().count() as isize;
+        if char_idx < chars.start {
+            break;
+        }
+        if char_idx <= chars.end {
+            return (chars.start as isize + delta + len) as usize;
+        }
+        delta += len - chars.len() as isize;
+    }
+    (char_idx as isize + delta) as usize
+}
+
+/// Map selections, given as `(anchor, head)` character indices from before `edits`, to `buffer` after them.
+pub fn map_selections(
+    selections: &[(usize, usize)],
+    buffer: &Buffer,
+    edits: &[CharEdit],
+) -> Vec<Range> {
+    selections
+        .iter()
+        .map(|(anchor, head)| {
+            Range::new(
+                buffer.char_to_point(map_char(*anchor, edits)),
+                buffer.char_to_point(map_char(*head, edits)),
+            )
+        })
+        .collect()
+}
+
+/// Merge any overlapping selections, keeping the most recent one last.
+pub fn merge(selections: &mut Vec<Range>) {
+    let mut merged: Vec<Range> = Vec::with_capacity(selections.len());
+    for mut selection in selections.drain(..) {
+        loop {
+            let len = merged.len();
+            merged.retain(|other| {
+                if overlaps(selection, *other) {
+                    selection = union(selection, *other);
+                    false
+                } else {
+                    true
+                }
+            });
+            if merged.len() == len {
+                break;
+            }
+        }
+        merged.push(selection);
+    }
+    *selections = merged;
+}
+
+fn overlaps(a: Range, b: Range) -> bool {
+    let (a, b) = (a.sorted(), b.sorted());
+    a.start == b.start || (a.start < b.end && b.start < a.end)
+}
+
+/// Combine two selections, keeping the direction of `a`.
+fn union(a: Range, b: Range) -> Range {
+    let (sorted_a, sorted_b) = (a.sorted(), b.sorted());
+    let start = sorted_a.start.min(sorted_b.start);
+    let end = sorted_a.end.max(sorted_b.end);
+
+    if a.end < a.start {
+        Range::new(end, start)
+    } else {
+        Range::new(start, end)
+    }
+}
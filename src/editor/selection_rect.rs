@@ -0,0 +1,21 @@
+use dioxus::prelude::*;
+
+#[component]
+pub fn SelectionRect(
+    cx: Scope,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    background: String,
+) -> Element {
+    render!(div {
+        position: "absolute",
+        top: "{y}px",
+        left: "{x}px",
+        width: "{width}px",
+        height: "{height}px",
+        background: "{background}",
+        pointer_events: "none"
+    })
+}
@@ -0,0 +1,71 @@
+//! Selection of syntax nodes, by character ranges in a [`Buffer`].
+
+use crate::Buffer;
+use std::ops::Range;
+use tree_sitter_c2rust::Node;
+
+/// Find the smallest named node that encloses more than the characters in `chars`.
+pub fn expand(buffer: &Buffer, chars: Range<usize>) -> Option<Range<usize>> {
+    let bytes = to_bytes(buffer, chars);
+    let mut node = buffer
+        .tree
+        .root_node()
+        .named_descendant_for_byte_range(bytes.start, bytes.end)?;
+
+    while node.byte_range() == bytes || !node.is_named() {
+        node = node.parent()?;
+    }
+    Some(to_chars(buffer, node))
+}
+
+/// Find the first named node inside the characters in `chars`.
+pub fn shrink(buffer: &Buffer, chars: Range<usize>) -> Option<Range<usize>> {
+    let bytes = to_bytes(buffer, chars);
+    let mut node = node_at(buffer, bytes.clone())?;
+
+    loop {
+        let mut cursor = node.walk();
+        let child = node
+            .named_children(&mut cursor)
+            .find(|child| child.start_byte() >= bytes.start && child.end_byte() <= bytes.end)?;
+        if child.byte_range() != bytes {
+            return Some(to_chars(buffer, child));
+        }
+        node = child;
+    }
+}
+
+/// Find the next or previous named sibling of the node at the characters in `chars`.
+pub fn sibling(buffer: &Buffer, chars: Range<usize>, is_next: bool) -> Option<Range<usize>> {
+    let node = node_at(buffer, to_bytes(buffer, chars))?;
+    let sibling = if is_next {
+        node.next_named_sibling()
+    } else {
+        node.prev_named_sibling()
+    }?;
+    Some(to_chars(buffer, sibling))
+}
+
+/// Find the outermost named node covering exactly the same bytes as the smallest one enclosing `bytes`.
+fn node_at(buffer: &Buffer, bytes: Range<usize>) -> Option<Node<'_>> {
+    let mut node = buffer
+        .tree
+        .root_node()
+        .named_descendant_for_byte_range(bytes.start, bytes.end)?;
+
+    while let Some(parent) = node.parent() {
+        if parent.byte_range() != node.byte_range() || parent.parent().is_none() {
+            break;
+        }
+        node = parent;
+    }
+    Some(node)
+}
+
+fn to_bytes(buffer: &Buffer, chars: Range<usize>) -> Range<usize> {
+    buffer.rope.char_to_byte(chars.start)..buffer.rope.char_to_byte(chars.end)
+}
+
+fn to_chars(buffer: &Buffer, node: Node) -> Range<usize> {
+    buffer.rope.byte_to_char(node.start_byte())..buffer.rope.byte_to_char(node.end_byte())
+}
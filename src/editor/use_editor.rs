@@ -1,22 +1,54 @@
-use crate::{language, use_buffer, use_highlights, Buffer, Highlight, Language, Range, Span};
-use dioxus::prelude::{use_context_provider, Scope};
-use dioxus_lazy::{
-    lazy::{self, Values},
-    Direction, UseLazyAsync, UseList,
+use super::{
+    keymap::Lookup,
+    motion::{self, Motion},
+    selection::{self, CharEdit},
+    structure,
+    vim::{self, UseVim},
+    Command, CommandTask, Commands, KeyChord, Keymap,
 };
+use crate::{
+    buffer::try_use_buffer,
+    clipboard, column, language,
+    layout::{Layout, Monospace, TextMeasurer},
+    search, use_highlights, Buffer, Clipboard, EditKind, Error, HighlightIndex, Language,
+    LanguageRegistry, PredicateArg, Predicates, Range, SearchError, SearchQuery, Theme,
+};
+use dioxus::{
+    html::input_data::keyboard_types::{Key, Modifiers},
+    prelude::{use_context_provider, Scope},
+};
+use dioxus_lazy::{lazy, Direction, UseLazyAsync, UseList};
 use dioxus_resize_observer::{use_resize, Rect};
 use dioxus_signals::{use_signal, Signal, Write};
-use std::cell::Ref;
+use std::{cell::Ref, collections::BTreeSet, rc::Rc};
+use tree_sitter_c2rust::Point;
 
-#[derive(Clone, Copy, PartialEq)]
+/// Text inserted for one level of indentation.
+const INDENT: &str = "    ";
+
+/// Builder for a [`UseEditor`].
+///
+/// This is [`Clone`] but not [`Copy`] or [`PartialEq`],
+/// since it owns the registries, keymap and clipboard that it passes to the editor.
+#[derive(Clone)]
 pub struct Builder {
     font_size: f64,
     height: f64,
     line_height: f64,
+    tab_width: usize,
     language: Language,
+    languages: LanguageRegistry,
+    predicates: Predicates,
+    theme: Theme,
+    clipboard: Option<Rc<dyn Clipboard>>,
+    keymap: Keymap,
+    commands: Commands,
+    is_vim: bool,
+    is_soft_wrap: bool,
 }
 
 impl Builder {
+    /// Font size of the editor text.
     pub fn font_size(mut self, font_size: f64) -> Self {
         self.font_size = font_size;
         self
@@ -32,23 +64,132 @@ impl Builder {
         self
     }
 
+    /// Set the number of spaces between tab stops.
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+
     pub fn language(mut self, language: Language) -> Self {
         self.language = language;
         self
     }
 
+    /// Set the languages that can be embedded in the text, like the code blocks of Markdown.
+    pub fn languages(mut self, languages: LanguageRegistry) -> Self {
+        self.languages = languages;
+        self
+    }
+
+    /// Register a query predicate, like `#is-upper?`, that highlight queries can use to filter their matches.
+    pub fn predicate(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&[PredicateArg]) -> bool + 'static,
+    ) -> Self {
+        self.predicates.register(name, f);
+        self
+    }
+
+    /// Set the colors of the editor and its syntax highlighting, instead of the light theme.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Set the clipboard used to copy and paste text, instead of the default for the current target.
+    pub fn clipboard(mut self, clipboard: impl Clipboard + 'static) -> Self {
+        self.clipboard = Some(Rc::new(clipboard));
+        self
+    }
+
+    /// Set the keymap that binds keys to commands, instead of the default keymap.
+    ///
+    /// The keymap is only read when the editor is created, so change it later with [`UseEditor::keymap`].
+    pub fn keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    /// Enable Vim modal editing, starting in normal mode.
+    pub fn vim(mut self, is_enabled: bool) -> Self {
+        self.is_vim = is_enabled;
+        self
+    }
+
+    /// Wrap lines that are wider than the editor at word boundaries, instead of scrolling horizontally.
+    pub fn soft_wrap(mut self, is_enabled: bool) -> Self {
+        self.is_soft_wrap = is_enabled;
+        self
+    }
+
+    /// Register a command that keys can be bound to.
+    ///
+    /// Commands are only read when the editor is created, so register them later with [`UseEditor::commands`].
+    pub fn command(mut self, command: impl Into<Command>, f: impl Fn(UseEditor) + 'static) -> Self {
+        self.commands.register(command, f);
+        self
+    }
+
+    /// Register a command that finishes asynchronously.
+    pub fn async_command<F>(
+        mut self,
+        command: impl Into<Command>,
+        f: impl Fn(UseEditor) -> F + 'static,
+    ) -> Self
+    where
+        F: std::future::Future<Output = ()> + 'static,
+    {
+        self.commands.register_async(command, f);
+        self
+    }
+
+    /// Create the editor.
+    ///
+    /// Panics if the language can't parse the text, see [`Builder::try_use_editor`].
     pub fn use_editor<'a, 's, T>(
         self,
         cx: Scope<'a, T>,
         make_text: impl FnOnce() -> &'s str,
     ) -> UseEditor {
+        self.try_use_editor(cx, make_text)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Create the editor, returning an error if the language can't parse the text.
+    ///
+    /// Errors after the editor is created, like an invalid highlight query, are stored in [`UseEditor::error`].
+    pub fn try_use_editor<'a, 's, T>(
+        self,
+        cx: Scope<'a, T>,
+        make_text: impl FnOnce() -> &'s str,
+    ) -> Result<UseEditor, Error> {
         let language = self.language;
         use_context_provider(cx, || Signal::new(language));
+        let error = *use_context_provider(cx, || Signal::new(None));
+        let predicates = *use_context_provider(cx, || Signal::new(self.predicates.clone()));
 
-        let buffer = use_buffer(cx, language.tree_sitter, make_text);
+        let buffer = try_use_buffer(cx, language.tree_sitter, make_text)?;
+        cx.use_hook(|| {
+            let result = buffer
+                .write()
+                .set_injections(language.injection_query, self.languages.clone());
+            if let Err(e) = result {
+                log::error!("Invalid injection query: {}", e);
+                error.set(Some(e));
+            }
+        });
         let is_focused = use_signal(cx, || false);
-        let selections = use_signal(cx, || Vec::new());
-        let highlights = use_highlights(cx, buffer);
+        let selections = use_signal(cx, || vec![Range::default()]);
+        let rows_per_page = (self.height / self.line_height).ceil() as usize + 1;
+        let visible_rows = use_signal(cx, || 0..rows_per_page);
+        let highlights = use_highlights(cx, buffer, visible_rows);
+        let layout = use_signal(cx, || {
+            let measurer: Box<dyn TextMeasurer> = Box::new(Monospace::new(self.font_size));
+            Layout::new(measurer, self.line_height)
+        });
+
+        // The list only tracks the scroll position of the editor, whose lines are laid out by `layout`.
         let list = UseList::builder()
             .direction(Direction::Row)
             .size(self.height)
@@ -56,48 +197,128 @@ impl Builder {
             .len(buffer().rope.len_lines())
             .use_list(
                 cx,
-                lazy::from_async_range_fn(move |range, is_rev| async move {
-                    let mut lines = buffer().lines(range, &highlights());
-                    if is_rev {
-                        lines.reverse();
-                    }
-                    lines
-                }),
+                lazy::from_async_range_fn(|range, _| async move { vec![(); range.len()] }),
             );
         let container_size = use_resize(cx, list.mounted);
+        let clipboard = use_signal(cx, || {
+            self.clipboard
+                .clone()
+                .unwrap_or_else(clipboard::default_clipboard)
+        });
+        let soft_wrap = use_signal(cx, || self.is_soft_wrap);
+        let theme = use_signal(cx, || self.theme.clone());
+        let keymap = use_signal(cx, || self.keymap.clone());
+        let commands = use_signal(cx, || self.commands.clone());
+        let pending_keys = use_signal(cx, Vec::new);
+        let expansions = use_signal(cx, Vec::new);
+        let is_search_open = use_signal(cx, || false);
+        let search_query = use_signal(cx, SearchQuery::default);
+        let search_matches = use_signal(cx, Vec::new);
+        let search_error = use_signal(cx, || None);
+        let vim = vim::use_vim(cx);
 
-        UseEditor {
+        let editor = UseEditor {
             buffer,
+            error,
             is_focused,
             container_size,
             list,
+            layout,
+            visible_rows,
             selections,
             highlights,
+            soft_wrap,
+            theme,
+            clipboard,
+            keymap,
+            commands,
+            pending_keys,
+            expansions,
+            is_search_open,
+            search_query,
+            search_matches,
+            search_error,
+            predicates,
+            vim: self.is_vim.then_some(vim),
+            font_size: self.font_size,
             height: self.height,
             line_height: self.line_height,
-        }
+            tab_width: self.tab_width,
+        };
+        dioxus_signals::use_effect(cx, move || editor.update_search());
+        Ok(editor)
     }
 }
 
 #[derive(Clone, Copy, PartialEq)]
 pub struct UseEditor {
     pub buffer: Signal<Buffer>,
+
+    /// Last error that occurred in the editor, like an invalid highlight query.
+    pub error: Signal<Option<Error>>,
+
     is_focused: Signal<bool>,
     pub container_size: Signal<Option<Rect>>,
-    pub list: UseList<UseLazyAsync<Vec<Span>>>,
+    pub list: UseList<UseLazyAsync<()>>,
+
+    /// Positions of the lines of the buffer, measured by the [`Editor`](super::Editor) as they become visible.
+    pub layout: Signal<Layout<Box<dyn TextMeasurer>>>,
+
+    /// Rows of the buffer that are visible in the [`Editor`](super::Editor), updated as it scrolls.
+    pub visible_rows: Signal<std::ops::Range<usize>>,
     pub selections: Signal<Vec<Range>>,
-    pub highlights: Signal<Vec<Highlight>>,
+    pub highlights: Signal<HighlightIndex>,
+
+    /// Whether lines wider than the editor are wrapped, which can be changed while it's running.
+    pub soft_wrap: Signal<bool>,
+
+    /// Colors of the editor, which can be changed while it's running.
+    pub theme: Signal<Theme>,
+
+    clipboard: Signal<Rc<dyn Clipboard>>,
+
+    /// Bindings of keys to commands, which can be changed while it's running.
+    pub keymap: Signal<Keymap>,
+
+    /// Commands that keys can be bound to, which can be registered while it's running.
+    pub commands: Signal<Commands>,
+
+    pending_keys: Signal<Vec<KeyChord>>,
+    expansions: Signal<Vec<(Vec<Range>, Vec<Range>)>>,
+    is_search_open: Signal<bool>,
+    pub search_query: Signal<SearchQuery>,
+    pub search_matches: Signal<Vec<Range>>,
+    pub search_error: Signal<Option<SearchError>>,
+
+    /// Predicates that filter the matches of highlight and search queries, like `#any-of?`.
+    pub predicates: Signal<Predicates>,
+    pub vim: Option<UseVim>,
+
+    /// Font size of the text, which the [`Editor`](super::Editor) measures it with.
+    pub font_size: f64,
     pub height: f64,
     pub line_height: f64,
+
+    /// Number of spaces between tab stops.
+    pub tab_width: usize,
 }
 
 impl UseEditor {
     pub fn builder() -> Builder {
         Builder {
-            font_size: 16.,
+            font_size: 14.,
             height: 400.,
             line_height: 24.,
+            tab_width: 4,
             language: language::rust(),
+            languages: LanguageRegistry::default(),
+            predicates: Predicates::default(),
+            theme: Theme::default(),
+            clipboard: None,
+            keymap: Keymap::default(),
+            commands: Commands::default(),
+            is_vim: false,
+            is_soft_wrap: false,
         }
     }
 
@@ -125,13 +346,627 @@ impl UseEditor {
         *self.list.scroll_range.scroll.read()
     }
 
+    /// Handle a key press, running the command it's bound to or inserting its text.
+    ///
+    /// Returns the task of a command that finishes asynchronously.
+    pub fn handle_key(&self, key: Key, modifiers: Modifiers) -> Option<CommandTask> {
+        if let Some(vim) = self.vim {
+            if vim.handle_key(self, &key, modifiers) {
+                return None;
+            }
+        }
+
+        let chord = KeyChord::new(key.clone(), modifiers);
+        if chord.is_modifier() {
+            return None;
+        }
+
+        let mut pending_keys = self.pending_keys.write();
+        pending_keys.push(chord);
+        let lookup = self.keymap.read().lookup(&pending_keys);
+
+        match lookup {
+            Lookup::Command(command) => {
+                pending_keys.clear();
+                drop(pending_keys);
+                self.run_command(&command)
+            }
+            Lookup::Pending => None,
+            Lookup::None => {
+                let is_sequence = pending_keys.len() > 1;
+                pending_keys.clear();
+                drop(pending_keys);
+
+                if let Key::Character(text) = key {
+                    if !is_sequence && !modifiers.intersects(Modifiers::CONTROL | Modifiers::META) {
+                        self.insert(&text);
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// Run a registered command, returning its task if it finishes asynchronously.
+    pub fn run_command(&self, command: &Command) -> Option<CommandTask> {
+        let handler = self.commands.read().handler(command);
+        handler.and_then(|handler| handler(*self))
+    }
+
+    /// Insert `text` at every cursor, replacing any selected text.
     pub fn insert(&self, text: &str) {
-        let cursor_ref = &mut self.selections.write()[0].start;
-        self.buffer
-            .write()
-            .insert(cursor_ref.row, cursor_ref.column, text);
-        cursor_ref.column += text.len();
-        cursor_ref.row += text.lines().count() - 1;
-        self.list.lazy.refresh();
+        self.edit(EditKind::Insert, |buffer, selections| {
+            selections
+                .iter()
+                .map(|selection| (buffer.range_to_chars(*selection), text.to_owned()))
+                .collect()
+        })
+    }
+
+    /// Text of every non-empty selection, separated by newlines.
+    pub fn selected_text(&self) -> String {
+        let buffer = self.buffer.read();
+        let texts: Vec<_> = self
+            .selections
+            .read()
+            .iter()
+            .filter(|selection| !selection.is_empty())
+            .map(|selection| {
+                buffer
+                    .rope
+                    .slice(buffer.range_to_chars(*selection))
+                    .to_string()
+            })
+            .collect();
+        texts.join("\n")
+    }
+
+    /// Copy the selected text to the clipboard.
+    pub fn copy(&self) {
+        let text = self.selected_text();
+        if !text.is_empty() {
+            self.clipboard.read().write(text);
+        }
+    }
+
+    /// Copy the selected text to the clipboard and delete it.
+    pub fn cut(&self) {
+        self.copy();
+        self.delete_selected_text();
+    }
+
+    /// Delete the text of every selection.
+    pub fn delete_selected_text(&self) {
+        self.edit(EditKind::Delete, |buffer, selections| {
+            selections
+                .iter()
+                .map(|selection| (buffer.range_to_chars(*selection), String::new()))
+                .collect()
+        })
+    }
+
+    /// Paste the text on the clipboard at every cursor.
+    pub async fn paste(self) {
+        let text = self.clipboard.read().read();
+        if let Some(text) = text.await {
+            self.paste_text(&text);
+        }
+    }
+
+    /// Paste `text` at every cursor.
+    ///
+    /// If `text` has one line for each cursor, each line is pasted at its own cursor.
+    pub fn paste_text(&self, text: &str) {
+        self.edit(EditKind::Other, |buffer, selections| {
+            let lines: Vec<_> = text.lines().collect();
+            selections
+                .iter()
+                .enumerate()
+                .map(|(idx, selection)| {
+                    let text = if selections.len() > 1 && lines.len() == selections.len() {
+                        lines[idx]
+                    } else {
+                        text
+                    };
+                    (buffer.range_to_chars(*selection), text.to_owned())
+                })
+                .collect()
+        })
+    }
+
+    /// Indent every line touched by a multi-line selection, or insert an indent at every other cursor.
+    pub fn indent(&self) {
+        self.edit(EditKind::Other, |buffer, selections| {
+            let mut edits = Vec::new();
+            let mut rows = BTreeSet::new();
+            for selection in selections {
+                let sorted = selection.sorted();
+                if sorted.start.row == sorted.end.row {
+                    edits.push((buffer.range_to_chars(sorted), INDENT.to_owned()));
+                } else {
+                    rows.extend(sorted.start.row..=sorted.end.row);
+                }
+            }
+
+            edits.extend(rows.into_iter().map(|row| {
+                let char_idx = buffer.rope.line_to_char(row);
+                (char_idx..char_idx, INDENT.to_owned())
+            }));
+            edits
+        })
+    }
+
+    /// Remove one level of indentation from every line touched by a selection.
+    pub fn outdent(&self) {
+        self.edit(EditKind::Other, |buffer, selections| {
+            let rows: BTreeSet<_> = selections
+                .iter()
+                .flat_map(|selection| {
+                    let sorted = selection.sorted();
+                    sorted.start.row..=sorted.end.row
+                })
+                .collect();
+
+            rows.into_iter()
+                .map(|row| {
+                    let line = buffer.rope.line(row);
+                    let len = if line.chars().next() == Some('\t') {
+                        1
+                    } else {
+                        line.chars()
+                            .take(INDENT.len())
+                            .take_while(|c| *c == ' ')
+                            .count()
+                    };
+                    let char_idx = buffer.rope.line_to_char(row);
+                    (char_idx..char_idx + len, String::new())
+                })
+                .collect()
+        })
+    }
+
+    /// Add a cursor at `point`.
+    pub fn add_cursor(&self, point: Point) {
+        let mut selections = self.selections.write();
+        selections.push(Range::point(point));
+        selection::merge(&mut selections);
+    }
+
+    /// Add a cursor on the line above the topmost cursor.
+    pub fn add_cursor_above(&self) {
+        self.add_cursor_vertically(Motion::Up)
+    }
+
+    /// Add a cursor on the line below the bottommost cursor.
+    pub fn add_cursor_below(&self) {
+        self.add_cursor_vertically(Motion::Down)
+    }
+
+    /// Select the word under the last cursor, or add a selection at the next occurrence of its selected text.
+    pub fn select_next_occurrence(&self) {
+        let buffer = self.buffer.read();
+        let mut selections = self.selections.write();
+        let Some(last) = selections.last_mut() else {
+            return;
+        };
+
+        if last.is_empty() {
+            let word = motion::word_at(&buffer.rope, buffer.point_to_char(last.head()));
+            if !word.is_empty() {
+                *last = Range::new(
+                    buffer.char_to_point(word.start),
+                    buffer.char_to_point(word.end),
+                );
+            }
+            return;
+        }
+
+        // Search forward from the selection, wrapping around to the start of the text.
+        let chars = buffer.range_to_chars(*last);
+        let needle = buffer.rope.slice(chars.clone()).to_string();
+        let text = buffer.rope.slice(..);
+        let from = buffer.rope.char_to_byte(chars.end);
+        let Some(byte_idx) = search::find_in_rope(text, &needle, from)
+            .or_else(|| search::find_in_rope(text, &needle, 0))
+        else {
+            return;
+        };
+
+        let start = buffer.rope.byte_to_char(byte_idx);
+        let end = start + needle.chars().count();
+        let head = buffer.char_to_point(end);
+        selections.push(Range::new(buffer.char_to_point(start), head));
+        selection::merge(&mut selections);
+        self.scroll_to_row(head.row);
+    }
+
+    /// Expand every selection to the enclosing syntax node.
+    pub fn expand_selection(&self) {
+        let before = self.selections.read().clone();
+        self.select_nodes(structure::expand);
+
+        let after = self.selections.read().clone();
+        if after != before {
+            self.expansions.write().push((before, after));
+        }
+    }
+
+    /// Shrink every selection back to what it was before it was expanded, or to its first child node.
+    pub fn shrink_selection(&self) {
+        let mut expansions = self.expansions.write();
+        let current = self.selections.read().clone();
+        if expansions.last().map(|(_, after)| after) != Some(&current) {
+            expansions.clear();
+        }
+
+        if let Some((before, _)) = expansions.pop() {
+            drop(expansions);
+            self.selections.set(before);
+        } else {
+            drop(expansions);
+            self.select_nodes(structure::shrink);
+        }
+    }
+
+    /// Select the next sibling of the syntax node at every selection.
+    pub fn select_next_sibling(&self) {
+        self.select_nodes(|buffer, chars| structure::sibling(buffer, chars, true));
+    }
+
+    /// Select the previous sibling of the syntax node at every selection.
+    pub fn select_previous_sibling(&self) {
+        self.select_nodes(|buffer, chars| structure::sibling(buffer, chars, false));
+    }
+
+    pub fn is_search_open(&self) -> bool {
+        *self.is_search_open.read()
+    }
+
+    /// Open the search panel, searching for the selected text if it's on one line.
+    pub fn open_search(&self) {
+        let text = self.selected_text();
+        if !text.is_empty() && !text.contains('\n') {
+            self.search_query.write().pattern = text;
+        }
+        self.is_search_open.set(true);
+    }
+
+    pub fn close_search(&self) {
+        self.is_search_open.set(false);
+    }
+
+    /// Replace the current search query.
+    pub fn set_search_query(&self, query: SearchQuery) {
+        self.search_query.set(query);
+    }
+
+    /// Select the next match of the search query after the last selection, wrapping around.
+    ///
+    /// This opens the search panel if it is closed.
+    pub fn find_next(&self) {
+        self.open_search_matches();
+        let Some(last) = self.selections.read().last().map(|last| last.sorted()) else {
+            return;
+        };
+
+        let matches = self.search_matches.read();
+        let next = matches
+            .iter()
+            .find(|range| {
+                range.start >= last.end && (range.start, range.end) != (last.start, last.end)
+            })
+            .or_else(|| matches.first())
+            .copied();
+        drop(matches);
+        if let Some(range) = next {
+            self.select_match(range);
+        }
+    }
+
+    /// Select the previous match of the search query before the last selection, wrapping around.
+    ///
+    /// This opens the search panel if it is closed.
+    pub fn find_previous(&self) {
+        self.open_search_matches();
+        let Some(last) = self.selections.read().last().map(|last| last.sorted()) else {
+            return;
+        };
+
+        let matches = self.search_matches.read();
+        let previous = matches
+            .iter()
+            .rev()
+            .find(|range| range.end <= last.start)
+            .or_else(|| matches.last())
+            .copied();
+        drop(matches);
+        if let Some(range) = previous {
+            self.select_match(range);
+        }
+    }
+
+    /// Replace the selected match of the search query with `replacement` and select the next one.
+    pub fn replace(&self, replacement: &str) {
+        let query = self.search_query.read().clone();
+        let buffer = self.buffer.read();
+        let Some(last) = self.selections.read().last().copied() else {
+            return;
+        };
+        let selected = buffer.range_to_chars(last);
+        let edit = query
+            .replacements(&buffer, &self.predicates.read(), replacement)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|(chars, _)| *chars == selected);
+        drop(buffer);
+
+        if let Some(edit) = edit {
+            self.edit(EditKind::Other, |_, _| vec![edit]);
+            // The matches are updated by an effect after the edit, which is too late to find the next one.
+            self.update_search();
+        }
+        self.find_next();
+    }
+
+    /// Replace every match of the search query with `replacement` as one undoable edit.
+    pub fn replace_all(&self, replacement: &str) {
+        let query = self.search_query.read().clone();
+        let replacements =
+            query.replacements(&self.buffer.read(), &self.predicates.read(), replacement);
+        match replacements {
+            Ok(edits) => self.edit(EditKind::Other, |_, _| edits),
+            Err(error) => self.search_error.set(Some(error)),
+        }
+    }
+
+    /// Move every cursor by `motion`, extending the selections instead if `extend` is `true`.
+    pub fn move_cursor(&self, motion: Motion, extend: bool) {
+        let page_rows = (self.height / self.line_height).floor() as usize;
+
+        let buffer = self.buffer.read();
+        let mut selections = self.selections.write();
+        for selection in selections.iter_mut() {
+            if !extend && !selection.is_empty() && (motion.is_backward() || motion.is_forward()) {
+                let sorted = selection.sorted();
+                let point = if motion.is_backward() {
+                    sorted.start
+                } else {
+                    sorted.end
+                };
+                *selection = Range::point(point);
+                continue;
+            }
+
+            let (head, goal_column) = motion.apply(
+                &buffer,
+                selection.head(),
+                selection.goal_column,
+                page_rows,
+                self.tab_width,
+            );
+            if extend {
+                selection.end = head;
+            } else {
+                *selection = Range::point(head);
+            }
+            selection.goal_column = goal_column;
+        }
+        selection::merge(&mut selections);
+
+        if let Some(selection) = selections.last() {
+            self.scroll_to_row(selection.head().row);
+        }
+    }
+
+    /// Scroll the editor so the line at `row` is visible.
+    pub fn scroll_to_row(&self, row: usize) {
+        let Some(mounted) = self.list.mounted.signal.read().clone() else {
+            return;
+        };
+        let Some(element) = mounted
+            .get_raw_element()
+            .ok()
+            .and_then(|raw| raw.downcast_ref::<web_sys::Element>())
+        else {
+            return;
+        };
+
+        // Lines above the row that haven't been measured yet are one row high.
+        let buffer = self.buffer.read();
+        let mut layout = self.layout.write();
+        layout.update(&buffer);
+        layout.measure(buffer.rope.slice(..), row..row + 1);
+        let top = layout.y(row);
+        let bottom = layout.y(row + 1);
+
+        let scroll = self.scroll() as f64;
+        if top < scroll {
+            element.set_scroll_top(top as i32);
+        } else if bottom > scroll + self.height {
+            element.set_scroll_top((bottom - self.height) as i32);
+        }
+    }
+
+    /// Delete the selected text, or the grapheme cluster before the cursor.
+    pub fn delete_backward(&self) {
+        self.delete_with(|buffer, idx| column::prev_grapheme(buffer.rope.slice(..), idx)..idx)
+    }
+
+    /// Delete the selected text, or the grapheme cluster after the cursor.
+    pub fn delete_forward(&self) {
+        self.delete_with(|buffer, idx| idx..column::next_grapheme(buffer.rope.slice(..), idx))
+    }
+
+    /// Delete the selected text, or the word before the cursor.
+    pub fn delete_word_backward(&self) {
+        self.delete_with(|buffer, idx| motion::prev_word_boundary(&buffer.rope, idx)..idx)
+    }
+
+    /// Delete the selected text, or the word after the cursor.
+    pub fn delete_word_forward(&self) {
+        self.delete_with(|buffer, idx| idx..motion::next_word_boundary(&buffer.rope, idx))
+    }
+
+    /// Undo the last edit, restoring the selections from before it.
+    pub fn undo(&self) {
+        let selections = self.buffer.write().undo();
+        if let Some(selections) = selections {
+            self.selections.set(selections);
+        }
+    }
+
+    /// Redo the last undone edit, restoring the selections from after it.
+    pub fn redo(&self) {
+        let selections = self.buffer.write().redo();
+        if let Some(selections) = selections {
+            self.selections.set(selections);
+        }
+    }
+
+    /// Open the search panel if it's closed, finding the matches it shows right away.
+    ///
+    /// While the panel is open, `search_matches` is kept up to date with the query and text.
+    fn open_search_matches(&self) {
+        if !self.is_search_open() {
+            self.is_search_open.set(true);
+            self.update_search();
+        }
+    }
+
+    fn select_match(&self, range: Range) {
+        self.selections.set(vec![range]);
+        self.scroll_to_row(range.end.row);
+    }
+
+    /// Update the search matches after the query or text changes.
+    fn update_search(&self) {
+        if !self.is_search_open() {
+            self.search_matches.set(Vec::new());
+            self.search_error.set(None);
+            return;
+        }
+
+        let buffer = self.buffer.read();
+        let matches = self
+            .search_query
+            .read()
+            .find(&buffer, &self.predicates.read());
+        match matches {
+            Ok(matches) => {
+                let matches = matches
+                    .into_iter()
+                    .map(|chars| {
+                        Range::new(
+                            buffer.char_to_point(chars.start),
+                            buffer.char_to_point(chars.end),
+                        )
+                    })
+                    .collect();
+                self.search_matches.set(matches);
+                self.search_error.set(None);
+            }
+            Err(error) => {
+                self.search_matches.set(Vec::new());
+                self.search_error.set(Some(error));
+            }
+        }
+    }
+
+    /// Replace every selection with the node found by `f`, keeping selections without one.
+    fn select_nodes(
+        &self,
+        f: impl Fn(&Buffer, std::ops::Range<usize>) -> Option<std::ops::Range<usize>>,
+    ) {
+        let buffer = self.buffer.read();
+        let mut selections = self.selections.write();
+        for selection in selections.iter_mut() {
+            if let Some(chars) = f(&buffer, buffer.range_to_chars(*selection)) {
+                *selection = Range::new(
+                    buffer.char_to_point(chars.start),
+                    buffer.char_to_point(chars.end),
+                );
+            }
+        }
+        selection::merge(&mut selections);
+
+        if let Some(last) = selections.last() {
+            self.scroll_to_row(last.head().row);
+        }
+    }
+
+    fn add_cursor_vertically(&self, motion: Motion) {
+        let buffer = self.buffer.read();
+        let mut selections = self.selections.write();
+
+        let heads = selections.iter().map(|selection| selection.head());
+        let edge = if motion == Motion::Up {
+            heads.min()
+        } else {
+            heads.max()
+        };
+        let Some(edge) = edge else {
+            return;
+        };
+
+        let (point, goal_column) = motion.apply(&buffer, edge, None, 1, self.tab_width);
+        if point.row != edge.row {
+            let mut cursor = Range::point(point);
+            cursor.goal_column = goal_column;
+            selections.push(cursor);
+            self.scroll_to_row(point.row);
+        }
+    }
+
+    /// Delete the selected text, or the character range returned by `f` for each empty selection.
+    fn delete_with(&self, f: impl Fn(&Buffer, usize) -> std::ops::Range<usize>) {
+        self.edit(EditKind::Delete, |buffer, selections| {
+            selections
+                .iter()
+                .map(|selection| {
+                    let chars = if selection.is_empty() {
+                        f(buffer, buffer.point_to_char(selection.head()))
+                    } else {
+                        buffer.range_to_chars(*selection)
+                    };
+                    (chars, String::new())
+                })
+                .collect()
+        })
+    }
+
+    /// Apply the edits returned by `f` for the current selections as one undoable transaction.
+    ///
+    /// Edits are applied in reverse document order and every selection is moved to match.
+    pub(super) fn edit(&self, kind: EditKind, f: impl FnOnce(&Buffer, &[Range]) -> Vec<CharEdit>) {
+        self.transact(kind, |buffer, selections| {
+            let edits = selection::normalize_edits(f(buffer, selections));
+            if edits.is_empty() {
+                return;
+            }
+
+            let chars: Vec<_> = selections
+                .iter()
+                .map(|selection| {
+                    (
+                        buffer.point_to_char(selection.anchor()),
+                        buffer.point_to_char(selection.head()),
+                    )
+                })
+                .collect();
+            buffer.replace_many(&edits);
+
+            *selections = selection::map_selections(&chars, buffer, &edits);
+            selection::merge(selections);
+        })
+    }
+
+    /// Run `f` as a single undoable transaction on the buffer and selections.
+    fn transact(&self, kind: EditKind, f: impl FnOnce(&mut Buffer, &mut Vec<Range>)) {
+        let mut buffer = self.buffer.write();
+        let mut selections = self.selections.write();
+
+        buffer.begin_transaction(kind, &selections);
+        f(&mut buffer, &mut selections);
+        buffer.commit_transaction(&selections);
     }
 }
@@ -0,0 +1,1156 @@
+use super::{
+    motion::{self, line_len, CharClass},
+    UseEditor,
+};
+use crate::{Buffer, EditKind, Range};
+use dioxus::{
+    html::input_data::keyboard_types::{Key, Modifiers},
+    prelude::Scope,
+};
+use dioxus_signals::{use_signal, Signal};
+use ropey::Rope;
+use std::collections::HashMap;
+
+/// Mode of the Vim editing layer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VimMode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+}
+
+/// Text stored in a Vim register.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    pub is_linewise: bool,
+}
+
+#[derive(Default)]
+struct VimState {
+    pending: String,
+    visual_anchor: usize,
+    visual_head: usize,
+    registers: HashMap<char, Register>,
+    recording: Option<Vec<(Key, Modifiers)>>,
+    last_change: Vec<(Key, Modifiers)>,
+    is_replaying: bool,
+}
+
+/// Vim modal editing layer of an editor.
+#[derive(Clone, Copy, PartialEq)]
+pub struct UseVim {
+    /// Current mode, for rendering a status line.
+    pub mode: Signal<VimMode>,
+    state: Signal<VimState>,
+}
+
+pub(super) fn use_vim<T>(cx: Scope<T>) -> UseVim {
+    UseVim {
+        mode: use_signal(cx, VimMode::default),
+        state: use_signal(cx, VimState::default),
+    }
+}
+
+impl UseVim {
+    /// Keys typed in normal or visual mode that don't form a full command yet.
+    pub fn pending_keys(&self) -> String {
+        self.state.read().pending.clone()
+    }
+
+    /// Contents of the register named `name`, where `"` is the unnamed register.
+    pub fn register(&self, name: char) -> Option<Register> {
+        self.state.read().registers.get(&name).cloned()
+    }
+
+    /// Handle a key press, returning `false` if it should be handled by the editor instead.
+    pub fn handle_key(&self, editor: &UseEditor, key: &Key, modifiers: Modifiers) -> bool {
+        let mode = *self.mode.read();
+        if mode == VimMode::Insert {
+            self.record(key, modifiers);
+            if *key == Key::Escape {
+                self.finish_change();
+                self.mode.set(VimMode::Normal);
+                self.move_cursor(editor, |buffer, idx| {
+                    let line_start = buffer.rope.line_to_char(buffer.char_to_point(idx).row);
+                    idx.saturating_sub(1).max(line_start)
+                });
+                return true;
+            }
+            return false;
+        }
+
+        let text = match key {
+            Key::Shift | Key::Control | Key::Alt | Key::Meta => return true,
+            Key::Escape => {
+                self.state.write().pending.clear();
+                if mode != VimMode::Normal {
+                    self.exit_visual(editor);
+                }
+                return true;
+            }
+            Key::Character(text)
+                if modifiers.contains(Modifiers::CONTROL) && text.eq_ignore_ascii_case("r") =>
+            {
+                editor.redo();
+                self.clamp_cursor(editor);
+                return true;
+            }
+            _ if modifiers.intersects(Modifiers::CONTROL | Modifiers::META) => return false,
+            Key::Character(text) => text.as_str(),
+            Key::ArrowLeft | Key::Backspace => "h",
+            Key::ArrowDown | Key::Enter => "j",
+            Key::ArrowUp => "k",
+            Key::ArrowRight => "l",
+            _ => return true,
+        };
+
+        let input = {
+            let mut state = self.state.write();
+            state.pending.push_str(text);
+            state.pending.clone()
+        };
+        let is_visual = mode != VimMode::Normal;
+        match parse(&input, is_visual) {
+            VimParse::Incomplete => {}
+            VimParse::Invalid => self.state.write().pending.clear(),
+            VimParse::Done(action) => {
+                self.state.write().pending.clear();
+                self.run(editor, &input, action);
+            }
+        }
+        true
+    }
+
+    fn run(&self, editor: &UseEditor, input: &str, action: VimAction) {
+        let count = action.count.unwrap_or(1);
+        if action.is_change() {
+            let mut state = self.state.write();
+            if !state.is_replaying {
+                state.recording = Some(
+                    input
+                        .chars()
+                        .map(|c| (Key::Character(c.to_string()), Modifiers::empty()))
+                        .collect(),
+                );
+            }
+        }
+
+        match action.command {
+            Command::Motion(motion) => {
+                if *self.mode.read() == VimMode::Normal {
+                    self.move_cursor(editor, |buffer, idx| {
+                        clamp_normal(
+                            buffer,
+                            motion_target(buffer, idx, motion, count, action.count),
+                        )
+                    });
+                } else {
+                    let (buffer, head) = (editor.buffer.read(), self.state.read().visual_head);
+                    let head = clamp_normal(
+                        &buffer,
+                        motion_target(&buffer, head, motion, count, action.count),
+                    );
+                    drop(buffer);
+                    self.state.write().visual_head = head;
+                    self.update_visual(editor);
+                }
+            }
+            Command::Operator(operator, target) => {
+                let buffer = editor.buffer.read();
+                let idx = buffer.point_to_char(cursor(editor));
+                let (chars, is_linewise) = match target {
+                    Target::Line => (line_range(&buffer, idx, count), true),
+                    Target::Motion(motion) => {
+                        // Changing nothing still starts inserting, like `C` on an empty line.
+                        let Some(chars) =
+                            motion_range(&buffer, idx, motion, operator, count, action.count)
+                                .or((operator == Operator::Change).then_some(idx..idx))
+                        else {
+                            return;
+                        };
+                        (chars, motion.is_linewise())
+                    }
+                    Target::Object(object) => {
+                        let Some(chars) = object.range(&buffer.rope, idx) else {
+                            return;
+                        };
+                        (chars, false)
+                    }
+                };
+                drop(buffer);
+                self.apply(editor, operator, chars, is_linewise, action.register);
+            }
+            Command::Object(object) => {
+                let buffer = editor.buffer.read();
+                let head = self.state.read().visual_head;
+                let Some(chars) = object.range(&buffer.rope, head) else {
+                    return;
+                };
+                drop(buffer);
+
+                if chars.start < chars.end {
+                    let mut state = self.state.write();
+                    state.visual_anchor = chars.start;
+                    state.visual_head = chars.end - 1;
+                }
+                self.update_visual(editor);
+            }
+            Command::VisualOperator(operator) => {
+                let (chars, is_linewise) = self.visual_range(editor);
+                self.mode.set(VimMode::Normal);
+                self.apply(editor, operator, chars, is_linewise, action.register);
+            }
+            Command::SwapVisual => {
+                let mut state = self.state.write();
+                let (anchor, head) = (state.visual_anchor, state.visual_head);
+                state.visual_anchor = head;
+                state.visual_head = anchor;
+                drop(state);
+                self.update_visual(editor);
+            }
+            Command::Other(c) => self.run_other(editor, c, count, action.register),
+        }
+
+        if *self.mode.read() == VimMode::Normal {
+            self.finish_change();
+        }
+    }
+
+    fn run_other(&self, editor: &UseEditor, c: char, count: usize, register: Option<char>) {
+        match c {
+            'i' => self.mode.set(VimMode::Insert),
+            'a' => {
+                self.move_cursor(editor, |buffer, idx| {
+                    let row = buffer.char_to_point(idx).row;
+                    let line_end = buffer.rope.line_to_char(row) + line_len(&buffer.rope, row);
+                    (idx + 1).min(line_end)
+                });
+                self.mode.set(VimMode::Insert);
+            }
+            'I' => {
+                self.move_cursor(editor, |buffer, idx| {
+                    first_non_blank(&buffer.rope, buffer.char_to_point(idx).row)
+                });
+                self.mode.set(VimMode::Insert);
+            }
+            'A' => {
+                self.move_cursor(editor, |buffer, idx| {
+                    let row = buffer.char_to_point(idx).row;
+                    buffer.rope.line_to_char(row) + line_len(&buffer.rope, row)
+                });
+                self.mode.set(VimMode::Insert);
+            }
+            'o' | 'O' => {
+                let buffer = editor.buffer.read();
+                let row = cursor(editor).row;
+                let indent: String = buffer
+                    .rope
+                    .line(row)
+                    .chars()
+                    .take_while(|c| *c == ' ' || *c == '\t')
+                    .collect();
+                let line_start = buffer.rope.line_to_char(row);
+                let (char_idx, text) = if c == 'o' {
+                    (
+                        line_start + line_len(&buffer.rope, row),
+                        format!("\n{indent}"),
+                    )
+                } else {
+                    (line_start, format!("{indent}\n"))
+                };
+                let cursor = char_idx
+                    + if c == 'o' {
+                        text.chars().count()
+                    } else {
+                        indent.chars().count()
+                    };
+                drop(buffer);
+
+                editor.edit(EditKind::Other, |_, _| vec![(char_idx..char_idx, text)]);
+                self.set_cursor(editor, cursor);
+                self.mode.set(VimMode::Insert);
+            }
+            'p' | 'P' if *self.mode.read() != VimMode::Normal => {
+                self.replace_visual(editor, count, register)
+            }
+            'p' | 'P' => self.put(editor, c == 'p', count, register),
+            'u' => {
+                for _ in 0..count {
+                    editor.undo();
+                }
+                self.clamp_cursor(editor);
+            }
+            'v' | 'V' => {
+                let mode = if c == 'v' {
+                    VimMode::Visual
+                } else {
+                    VimMode::VisualLine
+                };
+                if *self.mode.read() == mode {
+                    self.exit_visual(editor);
+                    return;
+                }
+
+                if *self.mode.read() == VimMode::Normal {
+                    let idx = editor.buffer.read().point_to_char(cursor(editor));
+                    let mut state = self.state.write();
+                    state.visual_anchor = idx;
+                    state.visual_head = idx;
+                }
+                self.mode.set(mode);
+                self.update_visual(editor);
+            }
+            '.' => {
+                let keys = {
+                    let mut state = self.state.write();
+                    state.is_replaying = true;
+                    state.last_change.clone()
+                };
+                for _ in 0..count {
+                    for (key, modifiers) in &keys {
+                        editor.handle_key(key.clone(), *modifiers);
+                    }
+                }
+                self.state.write().is_replaying = false;
+            }
+            _ => {}
+        }
+    }
+
+    /// Apply `operator` to the characters in `chars`.
+    fn apply(
+        &self,
+        editor: &UseEditor,
+        operator: Operator,
+        mut chars: std::ops::Range<usize>,
+        is_linewise: bool,
+        register: Option<char>,
+    ) {
+        let buffer = editor.buffer.read();
+        let text = buffer.rope.slice(chars.clone()).to_string();
+        drop(buffer);
+        self.store(register, text, is_linewise);
+
+        match operator {
+            Operator::Yank => self.set_cursor(editor, chars.start),
+            Operator::Delete => {
+                let buffer = editor.buffer.read();
+                if is_linewise && chars.end == buffer.rope.len_chars() && chars.start > 0 {
+                    chars.start -= 1;
+                }
+                drop(buffer);
+
+                editor.edit(EditKind::Delete, |_, _| {
+                    vec![(chars.clone(), String::new())]
+                });
+                let buffer = editor.buffer.read();
+                let idx = if is_linewise {
+                    let row = buffer
+                        .char_to_point(chars.start + usize::from(chars.start > 0))
+                        .row;
+                    first_non_blank(&buffer.rope, row)
+                } else {
+                    clamp_normal(&buffer, chars.start)
+                };
+                drop(buffer);
+                self.set_cursor(editor, idx);
+            }
+            Operator::Change => {
+                if is_linewise {
+                    let buffer = editor.buffer.read();
+                    let first_row = buffer.char_to_point(chars.start).row;
+                    let last_row = buffer.char_to_point(chars.end.saturating_sub(1)).row;
+                    chars.start = first_non_blank(&buffer.rope, first_row);
+                    chars.end =
+                        buffer.rope.line_to_char(last_row) + line_len(&buffer.rope, last_row);
+                    chars.end = chars.end.max(chars.start);
+                }
+
+                if !chars.is_empty() {
+                    editor.edit(EditKind::Delete, |_, _| {
+                        vec![(chars.clone(), String::new())]
+                    });
+                }
+                self.set_cursor(editor, chars.start);
+                self.mode.set(VimMode::Insert);
+            }
+        }
+    }
+
+    /// Replace the visual selection with the contents of a register.
+    fn replace_visual(&self, editor: &UseEditor, count: usize, register: Option<char>) {
+        let Some(register) = self.register(register.unwrap_or('"')) else {
+            return;
+        };
+        let (chars, is_linewise) = self.visual_range(editor);
+        self.mode.set(VimMode::Normal);
+
+        let buffer = editor.buffer.read();
+        let replaced = buffer.rope.slice(chars.clone()).to_string();
+        let mut text = register.text.repeat(count);
+        if register.is_linewise && !is_linewise {
+            text = format!("\n{text}");
+        } else if is_linewise && !register.is_linewise {
+            text.push('\n');
+        }
+        let cursor = (chars.start + text.chars().count()).saturating_sub(1);
+        drop(buffer);
+
+        editor.edit(EditKind::Other, |_, _| vec![(chars, text)]);
+        self.store(None, replaced, is_linewise);
+        self.move_cursor(editor, |buffer, _| clamp_normal(buffer, cursor));
+    }
+
+    fn put(&self, editor: &UseEditor, is_after: bool, count: usize, register: Option<char>) {
+        let Some(register) = self.register(register.unwrap_or('"')) else {
+            return;
+        };
+
+        let buffer = editor.buffer.read();
+        let point = cursor(editor);
+        let idx = buffer.point_to_char(point);
+        let mut text = register.text.repeat(count);
+
+        let (char_idx, cursor) = if register.is_linewise {
+            let row = if is_after { point.row + 1 } else { point.row };
+            let char_idx = if row < buffer.rope.len_lines() {
+                buffer.rope.line_to_char(row)
+            } else {
+                buffer.rope.len_chars()
+            };
+            if char_idx == buffer.rope.len_chars() && !text.is_empty() {
+                let ends_with_newline = buffer.rope.len_chars() > 0
+                    && buffer.rope.char(buffer.rope.len_chars() - 1) == '\n';
+                if !ends_with_newline {
+                    text = format!("\n{}", text.trim_end_matches('\n'));
+                }
+            }
+            let cursor = char_idx + usize::from(text.starts_with('\n'));
+            (char_idx, cursor)
+        } else {
+            let is_empty_line = line_len(&buffer.rope, point.row) == 0;
+            let char_idx = if is_after && !is_empty_line {
+                idx + 1
+            } else {
+                idx
+            };
+            (
+                char_idx,
+                (char_idx + text.chars().count()).saturating_sub(1),
+            )
+        };
+        drop(buffer);
+
+        editor.edit(EditKind::Other, |_, _| vec![(char_idx..char_idx, text)]);
+        self.set_cursor(editor, cursor);
+        if register.is_linewise {
+            self.move_cursor(editor, |buffer, idx| {
+                first_non_blank(&buffer.rope, buffer.char_to_point(idx).row)
+            });
+        }
+    }
+
+    /// Store `text` in the unnamed register and `register`, appending for uppercase names.
+    fn store(&self, register: Option<char>, text: String, is_linewise: bool) {
+        let mut state = self.state.write();
+        let mut value = Register { text, is_linewise };
+
+        match register {
+            Some(name) if name.is_ascii_uppercase() => {
+                let entry = state
+                    .registers
+                    .entry(name.to_ascii_lowercase())
+                    .or_default();
+                entry.text.push_str(&value.text);
+                entry.is_linewise |= is_linewise;
+                value = entry.clone();
+            }
+            Some(name) if name != '"' => {
+                state.registers.insert(name, value.clone());
+            }
+            _ => {}
+        }
+        state.registers.insert('"', value);
+    }
+
+    /// Range of characters selected in visual mode and whether it's linewise.
+    fn visual_range(&self, editor: &UseEditor) -> (std::ops::Range<usize>, bool) {
+        let buffer = editor.buffer.read();
+        let state = self.state.read();
+        let start = state.visual_anchor.min(state.visual_head);
+        let end = state.visual_anchor.max(state.visual_head);
+
+        if *self.mode.read() == VimMode::VisualLine {
+            let first_row = buffer.char_to_point(start).row;
+            let last_row = buffer.char_to_point(end).row;
+            (
+                buffer.rope.line_to_char(first_row)..line_end_inclusive(&buffer.rope, last_row),
+                true,
+            )
+        } else {
+            (start..(end + 1).min(buffer.rope.len_chars()), false)
+        }
+    }
+
+    fn update_visual(&self, editor: &UseEditor) {
+        let (chars, _) = self.visual_range(editor);
+        let buffer = editor.buffer.read();
+        let start = buffer.char_to_point(chars.start);
+        let end = buffer.char_to_point(chars.end);
+        let is_reversed = {
+            let state = self.state.read();
+            state.visual_head < state.visual_anchor
+        };
+        drop(buffer);
+
+        let range = if is_reversed {
+            Range::new(end, start)
+        } else {
+            Range::new(start, end)
+        };
+        editor.selections.set(vec![range]);
+    }
+
+    fn exit_visual(&self, editor: &UseEditor) {
+        let head = self.state.read().visual_head;
+        self.mode.set(VimMode::Normal);
+        self.set_cursor(editor, head);
+    }
+
+    fn record(&self, key: &Key, modifiers: Modifiers) {
+        let mut state = self.state.write();
+        if let Some(recording) = &mut state.recording {
+            recording.push((key.clone(), modifiers));
+        }
+    }
+
+    fn finish_change(&self) {
+        let mut state = self.state.write();
+        if let Some(recording) = state.recording.take() {
+            if !state.is_replaying {
+                state.last_change = recording;
+            }
+        }
+    }
+
+    fn move_cursor(&self, editor: &UseEditor, f: impl FnOnce(&Buffer, usize) -> usize) {
+        let buffer = editor.buffer.read();
+        let idx = f(&buffer, buffer.point_to_char(cursor(editor)));
+        drop(buffer);
+        self.set_cursor(editor, idx);
+    }
+
+    fn set_cursor(&self, editor: &UseEditor, char_idx: usize) {
+        let point = editor.buffer.read().char_to_point(char_idx);
+        editor.selections.set(vec![Range::point(point)]);
+        editor.scroll_to_row(point.row);
+    }
+
+    fn clamp_cursor(&self, editor: &UseEditor) {
+        self.move_cursor(editor, clamp_normal);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum VimMotion {
+    Left,
+    Right,
+    Down,
+    Up,
+    WordStart,
+    WordBack,
+    WordEnd,
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FirstLine,
+    LastLine,
+}
+
+impl VimMotion {
+    fn is_linewise(self) -> bool {
+        matches!(
+            self,
+            Self::Down | Self::Up | Self::FirstLine | Self::LastLine
+        )
+    }
+
+    fn is_inclusive(self) -> bool {
+        matches!(self, Self::WordEnd | Self::LineEnd)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Target {
+    Motion(VimMotion),
+    Line,
+    Object(TextObject),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct TextObject {
+    is_inner: bool,
+    kind: char,
+}
+
+impl TextObject {
+    fn range(self, rope: &Rope, idx: usize) -> Option<std::ops::Range<usize>> {
+        match self.kind {
+            'w' => Some(self.word(rope, idx)),
+            '(' | ')' | 'b' => self.pair(rope, idx, '(', ')'),
+            '{' | '}' | 'B' => self.pair(rope, idx, '{', '}'),
+            '[' | ']' => self.pair(rope, idx, '[', ']'),
+            '<' | '>' => self.pair(rope, idx, '<', '>'),
+            '"' | '\'' | '`' => self.quote(rope, idx, self.kind),
+            _ => None,
+        }
+    }
+
+    fn word(self, rope: &Rope, idx: usize) -> std::ops::Range<usize> {
+        let len = rope.len_chars();
+        if idx >= len {
+            return len..len;
+        }
+
+        let class = CharClass::of(rope.char(idx));
+        let same = |i: usize| rope.char(i) != '\n' && CharClass::of(rope.char(i)) == class;
+        let mut start = idx;
+        while start > 0 && same(start - 1) {
+            start -= 1;
+        }
+        let mut end = idx + 1;
+        while end < len && same(end) {
+            end += 1;
+        }
+
+        if !self.is_inner {
+            let is_blank = |i: usize| matches!(rope.char(i), ' ' | '\t');
+            let trailing_end = (end..len).find(|i| !is_blank(*i)).unwrap_or(len);
+            if trailing_end > end {
+                end = trailing_end;
+            } else {
+                while start > 0 && is_blank(start - 1) {
+                    start -= 1;
+                }
+            }
+        }
+        start..end
+    }
+
+    fn pair(
+        self,
+        rope: &Rope,
+        idx: usize,
+        open: char,
+        close: char,
+    ) -> Option<std::ops::Range<usize>> {
+        let len = rope.len_chars();
+        let mut depth = 0;
+        let mut start = None;
+        let mut i = (idx + 1).min(len);
+        while i > 0 {
+            i -= 1;
+            let c = rope.char(i);
+            if c == close && i != idx {
+                depth += 1;
+            } else if c == open {
+                if depth == 0 {
+                    start = Some(i);
+                    break;
+                }
+                depth -= 1;
+            }
+        }
+        let start = start?;
+
+        let mut depth = 0;
+        let end = (start + 1..len).find(|i| {
+            let c = rope.char(*i);
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                if depth == 0 {
+                    return true;
+                }
+                depth -= 1;
+            }
+            false
+        })?;
+
+        Some(if self.is_inner {
+            start + 1..end
+        } else {
+            start..end + 1
+        })
+    }
+
+    fn quote(self, rope: &Rope, idx: usize, quote: char) -> Option<std::ops::Range<usize>> {
+        let row = rope.char_to_line(idx);
+        let line_start = rope.line_to_char(row);
+        let quotes: Vec<_> = rope
+            .line(row)
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| *c == quote)
+            .map(|(col, _)| line_start + col)
+            .collect();
+
+        let (start, end) = quotes
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .find(|(start, end)| *start <= idx && idx <= *end)
+            .or_else(|| {
+                quotes
+                    .chunks_exact(2)
+                    .map(|pair| (pair[0], pair[1]))
+                    .find(|(start, _)| *start > idx)
+            })?;
+
+        Some(if self.is_inner {
+            start + 1..end
+        } else {
+            start..end + 1
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+    Motion(VimMotion),
+    Operator(Operator, Target),
+    Object(TextObject),
+    VisualOperator(Operator),
+    SwapVisual,
+    Other(char),
+}
+
+/// Command typed in normal or visual mode, like `"a3dw` or `ci(`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VimAction {
+    /// Register the command reads or writes, like `a` in `"a3dw`.
+    pub register: Option<char>,
+
+    /// Number of times to repeat the command, like `3` in `"a3dw`.
+    pub count: Option<usize>,
+    command: Command,
+}
+
+impl VimAction {
+    /// Parse the keys typed in normal or visual mode.
+    pub fn parse(input: &str, is_visual: bool) -> VimParse {
+        parse(input, is_visual)
+    }
+
+    /// Returns `true` if this action changes the text, so it can be repeated with `.`.
+    pub fn is_change(&self) -> bool {
+        match self.command {
+            Command::Operator(operator, _) => operator != Operator::Yank,
+            Command::Other(c) => "iaIAoOpP".contains(c),
+            _ => false,
+        }
+    }
+
+    /// Find where this action moves a cursor at `char_idx` in normal mode, if it's a motion.
+    pub fn target(&self, buffer: &Buffer, char_idx: usize) -> Option<usize> {
+        let Command::Motion(motion) = self.command else {
+            return None;
+        };
+        let count = self.count.unwrap_or(1);
+        Some(motion_target(buffer, char_idx, motion, count, self.count))
+    }
+
+    /// Find the characters this action applies to with a cursor at `char_idx`, if it's an operator like `dw`.
+    pub fn range(&self, buffer: &Buffer, char_idx: usize) -> Option<std::ops::Range<usize>> {
+        let Command::Operator(operator, target) = self.command else {
+            return None;
+        };
+        let count = self.count.unwrap_or(1);
+        match target {
+            Target::Line => Some(line_range(buffer, char_idx, count)),
+            Target::Motion(motion) => {
+                motion_range(buffer, char_idx, motion, operator, count, self.count)
+            }
+            Target::Object(object) => object.range(&buffer.rope, char_idx),
+        }
+    }
+}
+
+/// Keys typed in normal or visual mode, parsed by [`VimAction::parse`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VimParse {
+    /// The keys are the start of a command, like `d` or `"a`.
+    Incomplete,
+
+    /// The keys aren't a command, like `dz`.
+    Invalid,
+
+    /// The keys are a full command.
+    Done(VimAction),
+}
+
+/// Parse the keys typed in normal or visual mode, like `"a3dw` or `ci(`.
+fn parse(input: &str, is_visual: bool) -> VimParse {
+    let mut chars = input.chars().peekable();
+
+    let mut register = None;
+    if chars.peek() == Some(&'"') {
+        chars.next();
+        match chars.next() {
+            Some(name) if name.is_ascii_alphanumeric() || name == '"' => register = Some(name),
+            Some(_) => return VimParse::Invalid,
+            None => return VimParse::Incomplete,
+        }
+    }
+
+    let mut count = parse_count(&mut chars);
+    let Some(c) = chars.next() else {
+        return VimParse::Incomplete;
+    };
+
+    let done = |command| {
+        VimParse::Done(VimAction {
+            register,
+            count,
+            command,
+        })
+    };
+
+    // Expand shorthands like `x` for `dl` into an operator and motion.
+    let shorthand = match c {
+        'x' if !is_visual => Some((Operator::Delete, Target::Motion(VimMotion::Right))),
+        'X' if !is_visual => Some((Operator::Delete, Target::Motion(VimMotion::Left))),
+        'D' => Some((Operator::Delete, Target::Motion(VimMotion::LineEnd))),
+        'C' => Some((Operator::Change, Target::Motion(VimMotion::LineEnd))),
+        's' if !is_visual => Some((Operator::Change, Target::Motion(VimMotion::Right))),
+        'S' => Some((Operator::Change, Target::Line)),
+        'Y' => Some((Operator::Yank, Target::Line)),
+        _ => None,
+    };
+    if let Some((operator, target)) = shorthand {
+        return done(Command::Operator(operator, target));
+    }
+
+    let operator = match c {
+        'd' => Some(Operator::Delete),
+        'c' => Some(Operator::Change),
+        'y' => Some(Operator::Yank),
+        _ => None,
+    };
+    if let Some(operator) = operator {
+        if is_visual {
+            return done(Command::VisualOperator(operator));
+        }
+
+        let motion_count = parse_count(&mut chars);
+        if let Some(motion_count) = motion_count {
+            count = Some(count.unwrap_or(1) * motion_count);
+        }
+        let done = |command| {
+            VimParse::Done(VimAction {
+                register,
+                count,
+                command,
+            })
+        };
+
+        return match chars.next() {
+            None => VimParse::Incomplete,
+            Some(next) if next == c => done(Command::Operator(operator, Target::Line)),
+            Some(next @ ('i' | 'a')) => match chars.next() {
+                Some(kind) => done(Command::Operator(
+                    operator,
+                    Target::Object(TextObject {
+                        is_inner: next == 'i',
+                        kind,
+                    }),
+                )),
+                None => VimParse::Incomplete,
+            },
+            Some(next) => match parse_motion(next, &mut chars) {
+                MotionParse::Motion(motion) => {
+                    done(Command::Operator(operator, Target::Motion(motion)))
+                }
+                MotionParse::Incomplete => VimParse::Incomplete,
+                MotionParse::Invalid => VimParse::Invalid,
+            },
+        };
+    }
+
+    if is_visual {
+        match c {
+            'x' | 's' => {
+                let operator = if c == 'x' {
+                    Operator::Delete
+                } else {
+                    Operator::Change
+                };
+                return done(Command::VisualOperator(operator));
+            }
+            'o' => return done(Command::SwapVisual),
+            'i' | 'a' => {
+                return match chars.next() {
+                    Some(kind) => done(Command::Object(TextObject {
+                        is_inner: c == 'i',
+                        kind,
+                    })),
+                    None => VimParse::Incomplete,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if "iaIAoOpPuvV.".contains(c) {
+        return done(Command::Other(c));
+    }
+
+    match parse_motion(c, &mut chars) {
+        MotionParse::Motion(motion) => done(Command::Motion(motion)),
+        MotionParse::Incomplete => VimParse::Incomplete,
+        MotionParse::Invalid => VimParse::Invalid,
+    }
+}
+
+enum MotionParse {
+    Motion(VimMotion),
+    Incomplete,
+    Invalid,
+}
+
+fn parse_motion(c: char, chars: &mut impl Iterator<Item = char>) -> MotionParse {
+    let motion = match c {
+        'h' => VimMotion::Left,
+        'l' | ' ' => VimMotion::Right,
+        'j' => VimMotion::Down,
+        'k' => VimMotion::Up,
+        'w' => VimMotion::WordStart,
+        'b' => VimMotion::WordBack,
+        'e' => VimMotion::WordEnd,
+        '0' => VimMotion::LineStart,
+        '^' => VimMotion::FirstNonBlank,
+        '$' => VimMotion::LineEnd,
+        'G' => VimMotion::LastLine,
+        'g' => match chars.next() {
+            Some('g') => VimMotion::FirstLine,
+            Some(_) => return MotionParse::Invalid,
+            None => return MotionParse::Incomplete,
+        },
+        _ => return MotionParse::Invalid,
+    };
+    MotionParse::Motion(motion)
+}
+
+fn parse_count(chars: &mut std::iter::Peekable<impl Iterator<Item = char>>) -> Option<usize> {
+    let mut count = None;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        if digit == 0 && count.is_none() {
+            break;
+        }
+        chars.next();
+        count = Some(count.unwrap_or(0) * 10 + digit as usize);
+    }
+    count
+}
+
+/// Position of the primary cursor.
+fn cursor(editor: &UseEditor) -> crate::Point {
+    editor
+        .selections
+        .read()
+        .last()
+        .map(|selection| selection.head())
+        .unwrap_or_default()
+}
+
+/// Find where `motion` moves the cursor at `idx`.
+fn motion_target(
+    buffer: &Buffer,
+    idx: usize,
+    motion: VimMotion,
+    count: usize,
+    explicit_count: Option<usize>,
+) -> usize {
+    let rope = &buffer.rope;
+    let point = buffer.char_to_point(idx);
+    let line_start = rope.line_to_char(point.row);
+
+    let vertical = |row: usize| {
+        let len = line_len(rope, row);
+        rope.line_to_char(row) + point.column.min(len.saturating_sub(1))
+    };
+
+    match motion {
+        VimMotion::Left => idx.saturating_sub(count).max(line_start),
+        VimMotion::Right => (idx + count).min(line_start + line_len(rope, point.row)),
+        VimMotion::Down => vertical((point.row + count).min(last_row(rope))),
+        VimMotion::Up => vertical(point.row.saturating_sub(count)),
+        VimMotion::WordStart => (0..count).fold(idx, |idx, _| next_word_start(rope, idx)),
+        VimMotion::WordBack => (0..count).fold(idx, |idx, _| motion::prev_word_boundary(rope, idx)),
+        VimMotion::WordEnd => (0..count).fold(idx, |idx, _| next_word_end(rope, idx)),
+        VimMotion::LineStart => line_start,
+        VimMotion::FirstNonBlank => first_non_blank(rope, point.row),
+        VimMotion::LineEnd => {
+            // An empty line ends where it starts, instead of at the line ending before it.
+            let row = (point.row + count - 1).min(last_row(rope)).max(point.row);
+            rope.line_to_char(row) + line_len(rope, row).saturating_sub(1)
+        }
+        VimMotion::FirstLine => first_non_blank(
+            rope,
+            explicit_count.map_or(0, |n| n - 1).min(last_row(rope)),
+        ),
+        VimMotion::LastLine => first_non_blank(
+            rope,
+            explicit_count.map_or(last_row(rope), |n| (n - 1).min(last_row(rope))),
+        ),
+    }
+}
+
+/// Find the characters an operator applies to when combined with `motion`.
+fn motion_range(
+    buffer: &Buffer,
+    idx: usize,
+    motion: VimMotion,
+    operator: Operator,
+    count: usize,
+    explicit_count: Option<usize>,
+) -> Option<std::ops::Range<usize>> {
+    let rope = &buffer.rope;
+
+    // Like Vim, `cw` on a word changes to the end of the word instead of the start of the next.
+    let is_change_word = operator == Operator::Change
+        && motion == VimMotion::WordStart
+        && idx < rope.len_chars()
+        && !rope.char(idx).is_whitespace();
+    let motion = if is_change_word {
+        VimMotion::WordEnd
+    } else {
+        motion
+    };
+
+    if motion.is_linewise() {
+        let target = motion_target(buffer, idx, motion, count, explicit_count);
+        let (first, last) = {
+            let a = rope.char_to_line(idx);
+            let b = rope.char_to_line(target);
+            (a.min(b), a.max(b))
+        };
+        return Some(rope.line_to_char(first)..line_end_inclusive(rope, last));
+    }
+
+    let mut target = if is_change_word {
+        // Stay on the current word if the cursor is already on its last character.
+        let class = CharClass::of(rope.char(idx));
+        let is_word_end = idx + 1 >= rope.len_chars() || CharClass::of(rope.char(idx + 1)) != class;
+        if is_word_end {
+            motion_target(buffer, idx, motion, count - 1, explicit_count)
+        } else {
+            motion_target(buffer, idx, motion, count, explicit_count)
+        }
+    } else {
+        motion_target(buffer, idx, motion, count, explicit_count)
+    };
+
+    // A word motion from the last word of a line stops at the end of that line.
+    if motion == VimMotion::WordStart {
+        let row = rope.char_to_line(idx);
+        if rope.char_to_line(target) > row {
+            target = rope.line_to_char(row) + line_len(rope, row);
+        }
+    }
+
+    let start = idx.min(target);
+    let mut end = idx.max(target);
+    if motion.is_inclusive() {
+        // Include the character the motion stops on, but never a line ending.
+        let row = rope.char_to_line(end);
+        end = (end + 1).min(rope.line_to_char(row) + line_len(rope, row));
+    }
+    (start < end).then_some(start..end)
+}
+
+/// Characters of `count` lines starting at the line containing `idx`, including the last line ending.
+fn line_range(buffer: &Buffer, idx: usize, count: usize) -> std::ops::Range<usize> {
+    let rope = &buffer.rope;
+    let row = rope.char_to_line(idx);
+    let last = (row + count - 1).min(last_row(rope));
+    rope.line_to_char(row)..line_end_inclusive(rope, last)
+}
+
+fn line_end_inclusive(rope: &Rope, row: usize) -> usize {
+    if row + 1 < rope.len_lines() {
+        rope.line_to_char(row + 1)
+    } else {
+        rope.len_chars()
+    }
+}
+
+/// Last row that contains text, ignoring the empty line after a trailing newline.
+fn last_row(rope: &Rope) -> usize {
+    let len_lines = rope.len_lines();
+    if len_lines > 1 && line_len(rope, len_lines - 1) == 0 {
+        len_lines - 2
+    } else {
+        len_lines - 1
+    }
+}
+
+fn first_non_blank(rope: &Rope, row: usize) -> usize {
+    let indent = rope
+        .line(row)
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .count();
+    rope.line_to_char(row) + indent.min(line_len(rope, row))
+}
+
+/// Keep a normal mode cursor on a character instead of after the end of its line.
+fn clamp_normal(buffer: &Buffer, idx: usize) -> usize {
+    let point = buffer.char_to_point(idx);
+    let len = line_len(&buffer.rope, point.row);
+    buffer.rope.line_to_char(point.row) + point.column.min(len.saturating_sub(1))
+}
+
+fn next_word_start(rope: &Rope, mut idx: usize) -> usize {
+    let len = rope.len_chars();
+    if idx >= len {
+        return len;
+    }
+
+    let class = CharClass::of(rope.char(idx));
+    if class != CharClass::Whitespace {
+        while idx < len && CharClass::of(rope.char(idx)) == class {
+            idx += 1;
+        }
+    }
+    while idx < len && CharClass::of(rope.char(idx)) == CharClass::Whitespace {
+        idx += 1;
+    }
+    idx
+}
+
+fn next_word_end(rope: &Rope, idx: usize) -> usize {
+    let len = rope.len_chars();
+    let mut idx = idx + 1;
+    while idx < len && CharClass::of(rope.char(idx)) == CharClass::Whitespace {
+        idx += 1;
+    }
+    if idx >= len {
+        return len.saturating_sub(1);
+    }
+
+    let class = CharClass::of(rope.char(idx));
+    while idx + 1 < len && CharClass::of(rope.char(idx + 1)) == class {
+        idx += 1;
+    }
+    idx
+}
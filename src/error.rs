@@ -0,0 +1,51 @@
+use std::fmt;
+use tree_sitter_c2rust::{LanguageError, QueryError};
+
+/// Error that can occur while setting up or running an editor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The tree-sitter grammar isn't compatible with this version of tree-sitter.
+    Language(String),
+
+    /// The parser couldn't produce a syntax tree for the text.
+    Parse,
+
+    /// A tree-sitter query, like a highlight query, isn't valid for the language.
+    Query(String),
+
+    /// The browser APIs used to measure text aren't available.
+    Layout(String),
+
+    /// A theme file isn't a valid VS Code or TextMate theme.
+    Theme(String),
+
+    /// No [`Language`](crate::Language) was provided by a parent editor.
+    MissingLanguage,
+}
+
+impl From<LanguageError> for Error {
+    fn from(error: LanguageError) -> Self {
+        Self::Language(error.to_string())
+    }
+}
+
+impl From<QueryError> for Error {
+    fn from(error: QueryError) -> Self {
+        Self::Query(error.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Language(message) => write!(f, "incompatible language: {message}"),
+            Self::Parse => f.write_str("failed to parse text"),
+            Self::Query(message) => f.write_str(message),
+            Self::Layout(message) => write!(f, "failed to create layout: {message}"),
+            Self::Theme(message) => write!(f, "invalid theme: {message}"),
+            Self::MissingLanguage => f.write_str("no language was provided by an editor"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
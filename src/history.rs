@@ -0,0 +1,146 @@
+use crate::Range;
+use std::mem;
+
+/// Kind of edit in a transaction, used to group consecutive edits into one undo step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditKind {
+    Insert,
+    Delete,
+    Other,
+}
+
+/// Change to the text of a buffer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// Character index where the change starts.
+    pub start: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+impl Change {
+    /// Create the change that reverts this one.
+    pub fn inverse(&self) -> Self {
+        Self {
+            start: self.start,
+            removed: self.inserted.clone(),
+            inserted: self.removed.clone(),
+        }
+    }
+}
+
+/// Group of changes that are undone and redone together.
+#[derive(Clone, Debug)]
+pub struct Transaction {
+    pub kind: EditKind,
+    pub changes: Vec<Change>,
+    pub selections_before: Vec<Range>,
+    pub selections_after: Vec<Range>,
+}
+
+impl Transaction {
+    fn new(kind: EditKind, selections: &[Range]) -> Self {
+        Self {
+            kind,
+            changes: Vec::new(),
+            selections_before: selections.to_vec(),
+            selections_after: selections.to_vec(),
+        }
+    }
+
+    /// Returns `true` if `next` continues this transaction, like typing the next character of a word.
+    fn can_merge(&self, next: &Self) -> bool {
+        self.kind == next.kind
+            && self.kind != EditKind::Other
+            && self.selections_after == next.selections_before
+            && next
+                .changes
+                .iter()
+                .all(|change| !change.inserted.contains('\n'))
+    }
+}
+
+/// Undo and redo stacks of a buffer.
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+    pending: Option<Transaction>,
+    is_sealed: bool,
+}
+
+impl History {
+    /// Start a new transaction, saving the current `selections`.
+    pub fn begin(&mut self, kind: EditKind, selections: &[Range]) {
+        self.pending = Some(Transaction::new(kind, selections));
+    }
+
+    /// Record a change to the text.
+    ///
+    /// Changes made outside of a transaction become their own undo step.
+    pub fn record(&mut self, change: Change) {
+        if let Some(pending) = &mut self.pending {
+            pending.changes.push(change);
+        } else {
+            let mut transaction = Transaction::new(EditKind::Other, &[]);
+            transaction.changes.push(change);
+            self.push(transaction);
+        }
+    }
+
+    /// Finish the current transaction, saving the `selections` after its changes.
+    pub fn commit(&mut self, selections: &[Range]) {
+        let Some(mut transaction) = self.pending.take() else {
+            return;
+        };
+        if transaction.changes.is_empty() {
+            return;
+        }
+
+        transaction.selections_after = selections.to_vec();
+        self.push(transaction);
+    }
+
+    /// Prevent the next transaction from being merged into the last undo step.
+    pub fn seal(&mut self) {
+        self.is_sealed = true;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Take the last transaction to undo, moving it to the redo stack.
+    pub fn undo(&mut self) -> Option<Transaction> {
+        let transaction = self.undo_stack.pop()?;
+        self.redo_stack.push(transaction.clone());
+        self.is_sealed = true;
+        Some(transaction)
+    }
+
+    /// Take the last undone transaction to redo, moving it back to the undo stack.
+    pub fn redo(&mut self) -> Option<Transaction> {
+        let transaction = self.redo_stack.pop()?;
+        self.undo_stack.push(transaction.clone());
+        self.is_sealed = true;
+        Some(transaction)
+    }
+
+    fn push(&mut self, transaction: Transaction) {
+        self.redo_stack.clear();
+
+        let is_sealed = mem::replace(&mut self.is_sealed, false);
+        if let Some(last) = self.undo_stack.last_mut() {
+            if !is_sealed && last.can_merge(&transaction) {
+                last.changes.extend(transaction.changes);
+                last.selections_after = transaction.selections_after;
+                return;
+            }
+        }
+        self.undo_stack.push(transaction);
+    }
+}
@@ -0,0 +1,443 @@
+use crate::{buffer, Error, Highlight, Language, LanguageRegistry, Predicates, RopeProvider};
+use ropey::Rope;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    mem, ops,
+};
+use tree_sitter_c2rust::{
+    InputEdit, Node, Parser, Point, Query, QueryCursor, QueryMatch, QueryPredicate,
+    QueryPredicateArg, Range, Tree,
+};
+
+/// Syntax tree of text written in another language, like a fenced code block in Markdown.
+pub struct InjectionLayer {
+    pub language: Language,
+    pub tree: Tree,
+    /// Index of the pattern whose matches are parsed together with `injection.combined`.
+    combined: Option<usize>,
+    regions: Vec<Region>,
+    /// Whether the text of the layer was edited since it was parsed.
+    is_edited: bool,
+}
+
+/// Text injected by one match of the injection query.
+#[derive(PartialEq)]
+struct Region {
+    /// Bytes spanned by the nodes captured by the match.
+    extent: ops::Range<usize>,
+    ranges: Vec<ops::Range<usize>>,
+}
+
+/// Languages injected into the text of a buffer by an injection query.
+pub(crate) struct Injections {
+    query: Query,
+    registry: LanguageRegistry,
+    parser: Parser,
+    layers: Vec<InjectionLayer>,
+    highlight_queries: HashMap<&'static str, Option<Query>>,
+    /// Bytes edited since the last update.
+    edited: Vec<ops::Range<usize>>,
+}
+
+impl Injections {
+    /// Compile the injection `query` for the host `language`, injecting the languages in `registry`.
+    pub(crate) fn new(
+        language: tree_sitter_c2rust::Language,
+        query: &str,
+        registry: LanguageRegistry,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            query: Query::new(language, query)?,
+            registry,
+            parser: Parser::new(),
+            layers: Vec::new(),
+            highlight_queries: HashMap::new(),
+            edited: Vec::new(),
+        })
+    }
+
+    pub(crate) fn layers(&self) -> &[InjectionLayer] {
+        &self.layers
+    }
+
+    /// Edit the injected syntax trees and regions to match an edit of the text.
+    pub(crate) fn edit(&mut self, edit: &InputEdit) {
+        let edit_range = |range: &mut ops::Range<usize>| {
+            *range = edit_byte(range.start, edit)..edit_byte(range.end, edit)
+        };
+
+        for layer in &mut self.layers {
+            layer.tree.edit(edit);
+            layer.is_edited |= layer
+                .regions
+                .iter()
+                .flat_map(|region| &region.ranges)
+                .any(|range| range.start <= edit.old_end_byte && range.end >= edit.start_byte);
+            for region in &mut layer.regions {
+                edit_range(&mut region.extent);
+                region.ranges.iter_mut().for_each(edit_range);
+            }
+        }
+        self.edited.iter_mut().for_each(edit_range);
+        self.edited.push(edit.start_byte..edit.new_end_byte);
+    }
+
+    /// Find the injected regions of the host `tree` on the lines that were edited or in `changed_ranges`,
+    /// and reparse the layers they belong to.
+    ///
+    /// Layers that weren't edited or matched on those lines are kept as they are,
+    /// and layers that start at the same position as before are reparsed incrementally from their edited trees.
+    pub(crate) fn update(
+        &mut self,
+        tree: &Tree,
+        rope: &Rope,
+        changed_ranges: impl IntoIterator<Item = ops::Range<usize>>,
+    ) {
+        let Some(span) = mem::take(&mut self.edited)
+            .into_iter()
+            .chain(changed_ranges)
+            .reduce(|span, range| span.start.min(range.start)..span.end.max(range.end))
+        else {
+            return;
+        };
+        let span = whole_lines(rope, span);
+
+        // Forget the regions matched in the span, which the query finds again.
+        let mut layers = Vec::new();
+        let mut removed = Vec::new();
+        for mut layer in mem::take(&mut self.layers) {
+            let is_edited = mem::take(&mut layer.is_edited);
+            if layer.combined.is_some() {
+                let len = layer.regions.len();
+                layer.regions.retain(|region| !is_stale(region, &span));
+                let is_changed = is_edited || layer.regions.len() != len;
+                layers.push((layer, is_changed));
+            } else if layer.regions.iter().any(|region| is_stale(region, &span)) {
+                removed.push(layer);
+            } else {
+                layers.push((layer, is_edited));
+            }
+        }
+
+        for (language, combined, regions) in self.regions(tree, rope, span) {
+            let existing = layers.iter_mut().find(|(layer, _)| {
+                layer.language.name == language.name
+                    && layer.combined == combined
+                    && (combined.is_some() || layer.regions == regions)
+            });
+            if let Some((layer, is_changed)) = existing {
+                for region in regions {
+                    if !layer.regions.contains(&region) {
+                        layer.regions.push(region);
+                        *is_changed = true;
+                    }
+                }
+                continue;
+            }
+
+            let old_tree = removed
+                .iter()
+                .position(|layer| {
+                    layer.language.name == language.name
+                        && layer.regions[0].ranges[0].start == regions[0].ranges[0].start
+                })
+                .map(|idx| removed.swap_remove(idx).tree);
+            if let Some(layer) = self.parse(language, combined, regions, rope, old_tree.as_ref()) {
+                self.layers.push(layer);
+            }
+        }
+
+        for (layer, is_changed) in layers {
+            if !is_changed {
+                self.layers.push(layer);
+            } else if !layer.regions.is_empty() {
+                let InjectionLayer {
+                    language,
+                    tree,
+                    combined,
+                    regions,
+                    ..
+                } = layer;
+                if let Some(layer) = self.parse(language, combined, regions, rope, Some(&tree)) {
+                    self.layers.push(layer);
+                }
+            }
+        }
+    }
+
+    /// Parse the injected `regions` into a layer, reusing the edited `old_tree`.
+    fn parse(
+        &mut self,
+        language: Language,
+        combined: Option<usize>,
+        regions: Vec<Region>,
+        rope: &Rope,
+        old_tree: Option<&Tree>,
+    ) -> Option<InjectionLayer> {
+        let mut ranges: Vec<_> = regions
+            .iter()
+            .flat_map(|region| region.ranges.iter().cloned())
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+        ranges.dedup_by(|range, previous| range.start < previous.end);
+        let ranges: Vec<_> = ranges
+            .into_iter()
+            .map(|range| Range {
+                start_byte: range.start,
+                end_byte: range.end,
+                start_point: buffer::byte_to_point(rope, range.start),
+                end_point: buffer::byte_to_point(rope, range.end),
+            })
+            .collect();
+
+        self.parser.set_language(language.tree_sitter).ok()?;
+        self.parser.set_included_ranges(&ranges).ok()?;
+        let tree = buffer::parse(&mut self.parser, rope, old_tree)?;
+
+        self.highlight_queries
+            .entry(language.name)
+            .or_insert_with(|| {
+                Query::new(language.tree_sitter, language.highlight_query)
+                    .map_err(|error| log::error!("Invalid query: {}", error))
+                    .ok()
+            });
+        Some(InjectionLayer {
+            language,
+            tree,
+            combined,
+            regions,
+            is_edited: false,
+        })
+    }
+
+    /// Highlight the text of every injected layer in the byte range `bytes`.
+    pub(crate) fn highlights(
+        &self,
+        rope: &Rope,
+        bytes: ops::Range<usize>,
+        predicates: &Predicates,
+    ) -> Vec<Highlight> {
+        let text = rope.slice(..);
+        let mut query_cursor = QueryCursor::new();
+        query_cursor.set_byte_range(bytes);
+        let mut highlights = Vec::new();
+        for layer in &self.layers {
+            let Some(Some(query)) = self.highlight_queries.get(layer.language.name) else {
+                continue;
+            };
+            let matches = query_cursor
+                .matches(query, layer.tree.root_node(), RopeProvider { slice: text })
+                .filter(|mat| predicates.is_match(query, mat, text));
+            highlights.extend(Highlight::from_matches(query, matches));
+        }
+        highlights
+    }
+
+    /// Find the language and regions of every injection matched in the byte range `bytes`.
+    ///
+    /// Regions matched by a pattern with `injection.combined` are grouped together
+    /// with the index of their pattern, to be parsed as one document.
+    fn regions(
+        &self,
+        tree: &Tree,
+        rope: &Rope,
+        bytes: ops::Range<usize>,
+    ) -> Vec<(Language, Option<usize>, Vec<Region>)> {
+        let mut regions: Vec<(Language, Option<usize>, Vec<Region>)> = Vec::new();
+        let mut combined: HashMap<_, usize> = HashMap::new();
+        let mut query_cursor = QueryCursor::new();
+        query_cursor.set_byte_range(bytes);
+
+        let text = RopeProvider {
+            slice: rope.slice(..),
+        };
+        for mat in query_cursor.matches(&self.query, tree.root_node(), text) {
+            let Some((language, content, capture_index)) = self.injection(&mat, rope) else {
+                continue;
+            };
+            let properties = self.query.property_settings(mat.pattern_index);
+            let has_property = |key: &str| properties.iter().any(|property| &*property.key == key);
+
+            let range = self
+                .query
+                .general_predicates(mat.pattern_index)
+                .iter()
+                .fold(content.range(), |range, predicate| {
+                    offset(range, predicate, capture_index, rope)
+                });
+            let bytes = range.start_byte..range.end_byte;
+            if bytes.is_empty() {
+                continue;
+            }
+            let ranges = if has_property("injection.include-children") {
+                vec![bytes]
+            } else {
+                exclude_children(content, bytes)
+            };
+            if ranges.is_empty() {
+                continue;
+            }
+
+            let region = Region {
+                extent: mat
+                    .captures
+                    .iter()
+                    .map(|capture| capture.node.byte_range())
+                    .reduce(|extent, range| {
+                        extent.start.min(range.start)..extent.end.max(range.end)
+                    })
+                    .unwrap_or_default(),
+                ranges,
+            };
+            if has_property("injection.combined") {
+                match combined.entry((mat.pattern_index, language.name)) {
+                    Entry::Occupied(entry) => regions[*entry.get()].2.push(region),
+                    Entry::Vacant(entry) => {
+                        entry.insert(regions.len());
+                        regions.push((language, Some(mat.pattern_index), vec![region]));
+                    }
+                }
+            } else {
+                regions.push((language, None, vec![region]));
+            }
+        }
+        regions
+    }
+
+    /// Find the language, content node and content capture index of an injection match.
+    ///
+    /// The language is set with `#set! injection.language` or captured with `@injection.language`.
+    /// Older queries capture the content with the name of its language instead, like `@html`.
+    fn injection<'tree>(
+        &self,
+        mat: &QueryMatch<'_, 'tree>,
+        rope: &Rope,
+    ) -> Option<(Language, Node<'tree>, u32)> {
+        let mut name = self
+            .query
+            .property_settings(mat.pattern_index)
+            .iter()
+            .find(|property| &*property.key == "injection.language")
+            .and_then(|property| property.value.as_deref())
+            .map(str::to_owned);
+        let mut content = None;
+
+        for capture in mat.captures {
+            match &*self.query.capture_names()[capture.index as usize] {
+                "injection.content" | "content" => content = Some((capture.node, capture.index)),
+                "injection.language" | "language" => {
+                    name = Some(rope.byte_slice(capture.node.byte_range()).to_string())
+                }
+                capture_name if capture_name.starts_with('_') => {}
+                capture_name => {
+                    name.get_or_insert_with(|| capture_name.to_owned());
+                    content = Some((capture.node, capture.index));
+                }
+            }
+        }
+
+        let name = name?;
+        let name = name.trim();
+        let language = self
+            .registry
+            .get(name)
+            .or_else(|| self.registry.for_extension(name))?;
+        let (node, capture_index) = content?;
+        Some((language, node, capture_index))
+    }
+}
+
+/// Move the start and end of `range` by the rows and columns of an `#offset!` predicate,
+/// like `(#offset! @injection.content 0 1 0 -1)`.
+fn offset(range: Range, predicate: &QueryPredicate, capture_index: u32, rope: &Rope) -> Range {
+    let [QueryPredicateArg::Capture(index), offsets @ ..] = &*predicate.args else {
+        return range;
+    };
+    if &*predicate.operator != "offset!" || *index != capture_index {
+        return range;
+    }
+    let offsets: Option<Vec<isize>> = offsets
+        .iter()
+        .map(|arg| match arg {
+            QueryPredicateArg::String(offset) => offset.parse().ok(),
+            QueryPredicateArg::Capture(_) => None,
+        })
+        .collect();
+    let Some(&[start_row, start_col, end_row, end_col]) = offsets.as_deref() else {
+        return range;
+    };
+
+    let (start_byte, start_point) = move_point(rope, range.start_point, start_row, start_col);
+    let (end_byte, end_point) = move_point(rope, range.end_point, end_row, end_col);
+    Range {
+        start_byte,
+        end_byte,
+        start_point,
+        end_point,
+    }
+}
+
+/// Move `point` by `rows` and `columns`, returning its new byte offset and position.
+fn move_point(rope: &Rope, point: Point, rows: isize, columns: isize) -> (usize, Point) {
+    let row = point.row.saturating_add_signed(rows);
+    let byte_idx = if row < rope.len_lines() {
+        let column = point.column.saturating_add_signed(columns);
+        (rope.line_to_byte(row) + column).min(rope.len_bytes())
+    } else {
+        rope.len_bytes()
+    };
+    (byte_idx, buffer::byte_to_point(rope, byte_idx))
+}
+
+/// Split the bytes in `range` around the children of `node`, so only the text of the node itself is injected.
+fn exclude_children(node: Node, range: ops::Range<usize>) -> Vec<ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = range.start;
+    let mut cursor = node.walk();
+
+    for child in node.children(&mut cursor) {
+        if child.start_byte() >= range.end {
+            break;
+        }
+        if child.start_byte() > start {
+            ranges.push(start..child.start_byte());
+        }
+        start = start.max(child.end_byte());
+    }
+
+    if start < range.end {
+        ranges.push(start..range.end);
+    }
+    ranges
+}
+
+/// Move a byte offset to match an edit, moving offsets inside the edited text to its end.
+fn edit_byte(byte: usize, edit: &InputEdit) -> usize {
+    if byte <= edit.start_byte {
+        byte
+    } else if byte >= edit.old_end_byte {
+        byte - edit.old_end_byte + edit.new_end_byte
+    } else {
+        edit.new_end_byte
+    }
+}
+
+/// Check if the match of a region is in the byte range `span` that the query runs over again,
+/// the same way a query cursor checks nodes against its byte range, or if the match was deleted.
+fn is_stale(region: &Region, span: &ops::Range<usize>) -> bool {
+    region.extent.is_empty() || (region.extent.start < span.end && region.extent.end > span.start)
+}
+
+/// Extend the byte range `bytes` to the start and end of its lines, and the line endings around them,
+/// so nodes that end right before or start right after the lines overlap it.
+fn whole_lines(rope: &Rope, bytes: ops::Range<usize>) -> ops::Range<usize> {
+    let len = rope.len_bytes();
+    let start = rope.line_to_byte(rope.byte_to_line(bytes.start.min(len)));
+    let end_row = rope.byte_to_line(bytes.end.min(len)) + 1;
+    let end = if end_row < rope.len_lines() {
+        rope.line_to_byte(end_row)
+    } else {
+        len
+    };
+    start.saturating_sub(1)..(end + 1).min(len)
+}
@@ -1,19 +1,372 @@
 use dioxus::prelude::{use_context, Scope};
 use dioxus_signals::Signal;
 
-pub fn use_language<T>(cx: Scope<T>) -> Signal<Language> {
-    *use_context(cx).unwrap()
+/// Get the language provided by a parent editor, if any.
+pub fn use_language<T>(cx: Scope<T>) -> Option<Signal<Language>> {
+    use_context(cx).copied()
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Language {
+    /// Name of the language, like `"rust"`.
+    pub name: &'static str,
     pub tree_sitter: tree_sitter_c2rust::Language,
     pub highlight_query: &'static str,
+
+    /// Query that finds text written in other languages, like the code blocks of Markdown.
+    pub injection_query: &'static str,
+
+    /// File extensions of the language without a leading dot, like `"rs"`.
+    pub extensions: &'static [&'static str],
+
+    /// Interpreters that run the language from a shebang line, like `"python"`.
+    pub interpreters: &'static [&'static str],
 }
 
-pub fn rust() -> Language {
-    Language {
-        tree_sitter: tree_sitter_rust::language(),
-        highlight_query: tree_sitter_rust::HIGHLIGHT_QUERY,
+impl Language {
+    /// Create a language from a tree-sitter grammar and its highlight query.
+    pub fn new(
+        name: &'static str,
+        tree_sitter: tree_sitter_c2rust::Language,
+        highlight_query: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            tree_sitter,
+            highlight_query,
+            injection_query: "",
+            extensions: &[],
+            interpreters: &[],
+        }
+    }
+
+    pub fn injection_query(mut self, injection_query: &'static str) -> Self {
+        self.injection_query = injection_query;
+        self
+    }
+
+    pub fn extensions(mut self, extensions: &'static [&'static str]) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    pub fn interpreters(mut self, interpreters: &'static [&'static str]) -> Self {
+        self.interpreters = interpreters;
+        self
+    }
+}
+
+/// Collection of languages that can be looked up by name, file extension or shebang.
+///
+/// The [`Default`] registry contains every bundled language enabled by a cargo feature.
+/// Grammars other than Rust are compiled from C, so their features are only available on native targets.
+#[derive(Clone)]
+pub struct LanguageRegistry {
+    languages: Vec<Language>,
+}
+
+impl LanguageRegistry {
+    /// Create a registry without any languages.
+    pub fn empty() -> Self {
+        Self {
+            languages: Vec::new(),
+        }
+    }
+
+    /// Register a language, replacing any existing language with the same name.
+    pub fn register(&mut self, language: Language) -> &mut Self {
+        self.languages
+            .retain(|registered| !registered.name.eq_ignore_ascii_case(language.name));
+        self.languages.push(language);
+        self
+    }
+
+    /// Find the language named `name`, ignoring case.
+    pub fn get(&self, name: &str) -> Option<Language> {
+        self.languages
+            .iter()
+            .rev()
+            .find(|language| language.name.eq_ignore_ascii_case(name))
+            .copied()
+    }
+
+    /// Find the language of files with the extension `extension`, like `"rs"` or `".rs"`.
+    pub fn for_extension(&self, extension: &str) -> Option<Language> {
+        let extension = extension.trim_start_matches('.');
+        self.languages
+            .iter()
+            .rev()
+            .find(|language| {
+                language
+                    .extensions
+                    .iter()
+                    .any(|ext| ext.eq_ignore_ascii_case(extension))
+            })
+            .copied()
+    }
+
+    /// Find the language of the file at `path` from its extension.
+    pub fn for_path(&self, path: &str) -> Option<Language> {
+        let file_name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        let (_, extension) = file_name.rsplit_once('.')?;
+        self.for_extension(extension)
+    }
+
+    /// Find the language of `text` from the interpreter in its shebang line, like `#!/usr/bin/env python3`.
+    pub fn for_shebang(&self, text: &str) -> Option<Language> {
+        let line = text.lines().next()?.strip_prefix("#!")?;
+        let mut args = line.split_whitespace();
+        let mut program = args.next()?.rsplit('/').next()?;
+        if program == "env" {
+            program = args.find(|arg| !arg.starts_with('-') && !arg.contains('='))?;
+        }
+
+        // Ignore the version of interpreters like `python3.11`.
+        let interpreter = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        self.languages
+            .iter()
+            .rev()
+            .find(|language| {
+                language
+                    .interpreters
+                    .iter()
+                    .any(|name| *name == program || *name == interpreter)
+            })
+            .copied()
+    }
+
+    /// Detect the language of a file from its path, falling back to the shebang line of its text.
+    pub fn detect(&self, path: Option<&str>, text: &str) -> Option<Language> {
+        path.and_then(|path| self.for_path(path))
+            .or_else(|| self.for_shebang(text))
+    }
+
+    /// Iterate over the languages in this registry.
+    pub fn languages(&self) -> impl Iterator<Item = &Language> {
+        self.languages.iter()
+    }
+}
+
+impl Default for LanguageRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(rust());
+
+        #[cfg(all(feature = "c", not(target_arch = "wasm32")))]
+        registry.register(c());
+        #[cfg(all(feature = "html", not(target_arch = "wasm32")))]
+        registry.register(html());
+        #[cfg(all(feature = "javascript", not(target_arch = "wasm32")))]
+        registry.register(javascript());
+        #[cfg(all(feature = "json", not(target_arch = "wasm32")))]
+        registry.register(json());
+        #[cfg(all(feature = "markdown", not(target_arch = "wasm32")))]
+        registry.register(markdown());
+        #[cfg(all(feature = "python", not(target_arch = "wasm32")))]
+        registry.register(python());
+        #[cfg(all(feature = "toml", not(target_arch = "wasm32")))]
+        registry.register(toml());
+        #[cfg(all(feature = "typescript", not(target_arch = "wasm32")))]
+        registry.register(typescript()).register(tsx());
+
+        registry
     }
 }
+
+pub fn rust() -> Language {
+    Language::new(
+        "rust",
+        tree_sitter_rust::language(),
+        tree_sitter_rust::HIGHLIGHT_QUERY,
+    )
+    .injection_query(RUST_INJECTION_QUERY)
+    .extensions(&["rs"])
+    .interpreters(&["rust-script"])
+}
+
+// Markdown in doc comments, HTML in `html!` macros and Rust in Dioxus `rsx!` and `render!` macros,
+// whose elements are parsed like struct expressions.
+// Doc comments are only injected with the `markdown` feature, whose grammar is native-only,
+// so they're left as comments on wasm or when the registry doesn't have markdown.
+const RUST_INJECTION_QUERY: &str = r#"
+((line_comment) @injection.content
+ (#match? @injection.content "^//[/!]")
+ (#offset! @injection.content 0 3 0 1)
+ (#set! injection.language "markdown")
+ (#set! injection.combined)
+ (#set! injection.include-children))
+
+((macro_invocation
+   macro: (identifier) @_macro
+   (token_tree) @injection.content)
+ (#eq? @_macro "html")
+ (#offset! @injection.content 0 1 0 -1)
+ (#set! injection.language "html")
+ (#set! injection.include-children))
+
+((macro_invocation
+   macro: [(identifier) (scoped_identifier name: (identifier))] @_macro
+   (token_tree) @injection.content)
+ (#match? @_macro "(^|::)(rsx|render)$")
+ (#offset! @injection.content 0 1 0 -1)
+ (#set! injection.language "rust")
+ (#set! injection.include-children))
+"#;
+
+// Grammar crates return the `tree-sitter` crate's language, so their parsers are linked directly instead.
+// Each grammar's C function is `const TSLanguage *tree_sitter_x(void)`, so redeclaring it to return
+// `tree_sitter_c2rust::Language`, a `#[repr(transparent)]` wrapper of `*const TSLanguage`, returns the same pointer.
+// The `TSLanguage` struct is translated from the same tree-sitter 0.20 C source in both crates,
+// the assertion below checks that the wrapper is pointer-sized, and a parser rejects a language
+// with an incompatible ABI version when it's set, so the pointer is only read as the struct it is.
+//
+// Grammars other than Rust are compiled from C with the `tree-sitter` crate's runtime,
+// which doesn't build for `wasm32-unknown-unknown`, so their features do nothing on wasm.
+#[cfg(not(target_arch = "wasm32"))]
+const _: () = assert!(
+    std::mem::size_of::<tree_sitter_c2rust::Language>() == std::mem::size_of::<*const ()>()
+);
+
+#[cfg(not(target_arch = "wasm32"))]
+extern "C" {
+    #[cfg(all(feature = "c", not(target_arch = "wasm32")))]
+    fn tree_sitter_c() -> tree_sitter_c2rust::Language;
+    #[cfg(all(feature = "html", not(target_arch = "wasm32")))]
+    fn tree_sitter_html() -> tree_sitter_c2rust::Language;
+    #[cfg(all(feature = "javascript", not(target_arch = "wasm32")))]
+    fn tree_sitter_javascript() -> tree_sitter_c2rust::Language;
+    #[cfg(all(feature = "json", not(target_arch = "wasm32")))]
+    fn tree_sitter_json() -> tree_sitter_c2rust::Language;
+    #[cfg(all(feature = "markdown", not(target_arch = "wasm32")))]
+    fn tree_sitter_markdown() -> tree_sitter_c2rust::Language;
+    #[cfg(all(feature = "python", not(target_arch = "wasm32")))]
+    fn tree_sitter_python() -> tree_sitter_c2rust::Language;
+    #[cfg(all(feature = "toml", not(target_arch = "wasm32")))]
+    fn tree_sitter_toml() -> tree_sitter_c2rust::Language;
+    #[cfg(all(feature = "typescript", not(target_arch = "wasm32")))]
+    fn tree_sitter_typescript() -> tree_sitter_c2rust::Language;
+    #[cfg(all(feature = "typescript", not(target_arch = "wasm32")))]
+    fn tree_sitter_tsx() -> tree_sitter_c2rust::Language;
+}
+
+#[cfg(all(feature = "c", not(target_arch = "wasm32")))]
+pub fn c() -> Language {
+    Language::new(
+        "c",
+        unsafe { tree_sitter_c() },
+        tree_sitter_c::HIGHLIGHT_QUERY,
+    )
+    .extensions(&["c", "h"])
+    .interpreters(&["tcc"])
+}
+
+#[cfg(all(feature = "html", not(target_arch = "wasm32")))]
+pub fn html() -> Language {
+    Language::new(
+        "html",
+        unsafe { tree_sitter_html() },
+        tree_sitter_html::HIGHLIGHT_QUERY,
+    )
+    .injection_query(tree_sitter_html::INJECTION_QUERY)
+    .extensions(&["html", "htm"])
+}
+
+#[cfg(all(feature = "javascript", not(target_arch = "wasm32")))]
+pub fn javascript() -> Language {
+    Language::new(
+        "javascript",
+        unsafe { tree_sitter_javascript() },
+        tree_sitter_javascript::HIGHLIGHT_QUERY,
+    )
+    .injection_query(tree_sitter_javascript::INJECTION_QUERY)
+    .extensions(&["js", "mjs", "cjs", "jsx"])
+    .interpreters(&["node", "deno", "bun"])
+}
+
+#[cfg(all(feature = "json", not(target_arch = "wasm32")))]
+pub fn json() -> Language {
+    Language::new(
+        "json",
+        unsafe { tree_sitter_json() },
+        tree_sitter_json::HIGHLIGHT_QUERY,
+    )
+    .extensions(&["json"])
+}
+
+#[cfg(all(feature = "markdown", not(target_arch = "wasm32")))]
+pub fn markdown() -> Language {
+    Language::new(
+        "markdown",
+        unsafe { tree_sitter_markdown() },
+        tree_sitter_md::HIGHLIGHTS_QUERY,
+    )
+    .injection_query(MARKDOWN_INJECTION_QUERY)
+    .extensions(&["md", "markdown"])
+}
+
+// Code blocks in the language of their info string, like ```` ```rust ````, and HTML blocks.
+// Like the markdown grammar, this is only available on native targets.
+#[cfg(all(feature = "markdown", not(target_arch = "wasm32")))]
+const MARKDOWN_INJECTION_QUERY: &str = r#"
+((fenced_code_block
+  (info_string
+    (language) @injection.language)
+  (code_fence_content) @injection.content)
+ (#set! injection.include-children))
+
+((html_block) @injection.content
+ (#set! injection.language "html")
+ (#set! injection.include-children))
+"#;
+
+#[cfg(all(feature = "python", not(target_arch = "wasm32")))]
+pub fn python() -> Language {
+    Language::new(
+        "python",
+        unsafe { tree_sitter_python() },
+        tree_sitter_python::HIGHLIGHT_QUERY,
+    )
+    .extensions(&["py", "pyi"])
+    .interpreters(&["python"])
+}
+
+#[cfg(all(feature = "toml", not(target_arch = "wasm32")))]
+pub fn toml() -> Language {
+    Language::new(
+        "toml",
+        unsafe { tree_sitter_toml() },
+        tree_sitter_toml::HIGHLIGHT_QUERY,
+    )
+    .extensions(&["toml"])
+}
+
+#[cfg(all(feature = "typescript", not(target_arch = "wasm32")))]
+lazy_static::lazy_static! {
+    // The TypeScript highlight query only covers what it adds to JavaScript.
+    static ref TYPESCRIPT_HIGHLIGHT_QUERY: String = format!(
+        "{}\n{}",
+        tree_sitter_javascript::HIGHLIGHT_QUERY,
+        tree_sitter_typescript::HIGHLIGHT_QUERY
+    );
+}
+
+#[cfg(all(feature = "typescript", not(target_arch = "wasm32")))]
+pub fn typescript() -> Language {
+    Language::new(
+        "typescript",
+        unsafe { tree_sitter_typescript() },
+        &TYPESCRIPT_HIGHLIGHT_QUERY,
+    )
+    .extensions(&["ts", "mts", "cts"])
+    .interpreters(&["ts-node"])
+}
+
+#[cfg(all(feature = "typescript", not(target_arch = "wasm32")))]
+pub fn tsx() -> Language {
+    Language::new(
+        "tsx",
+        unsafe { tree_sitter_tsx() },
+        &TYPESCRIPT_HIGHLIGHT_QUERY,
+    )
+    .extensions(&["tsx"])
+}
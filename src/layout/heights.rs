@@ -0,0 +1,241 @@
+/// Heights of the lines of a layout, in a treap ordered by row with the total height of each subtree.
+///
+/// Finding, setting, inserting and removing lines takes `O(log n)` time.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Heights {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    root: Option<usize>,
+    seed: u32,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    height: f64,
+    priority: u32,
+    left: Option<usize>,
+    right: Option<usize>,
+
+    /// Number of lines in this subtree.
+    len: usize,
+
+    /// Total height of the lines in this subtree.
+    sum: f64,
+}
+
+impl Heights {
+    /// Create `len` lines of the same `height`.
+    pub(super) fn new(len: usize, height: f64) -> Self {
+        let mut heights = Self::default();
+        heights.root = heights.build(len, height);
+        heights
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.len_of(self.root)
+    }
+
+    /// Set the height of the line at `idx`.
+    pub(super) fn set(&mut self, idx: usize, height: f64) {
+        let mut path = Vec::new();
+        let mut node = self.root;
+        let mut idx = idx;
+        while let Some(n) = node {
+            path.push(n);
+            let left_len = self.len_of(self.nodes[n].left);
+            if idx < left_len {
+                node = self.nodes[n].left;
+            } else if idx == left_len {
+                self.nodes[n].height = height;
+                break;
+            } else {
+                idx -= left_len + 1;
+                node = self.nodes[n].right;
+            }
+        }
+
+        for n in path.into_iter().rev() {
+            self.update(n);
+        }
+    }
+
+    /// Replace the lines in `range` with `len` lines of the same `height`.
+    pub(super) fn splice(&mut self, range: std::ops::Range<usize>, len: usize, height: f64) {
+        let (before, rest) = self.split(self.root, range.start);
+        let (removed, after) = self.split(rest, range.len());
+        self.remove(removed);
+
+        let inserted = self.build(len, height);
+        let before = self.merge(before, inserted);
+        self.root = self.merge(before, after);
+    }
+
+    /// Total height of the lines before `idx`.
+    pub(super) fn y(&self, idx: usize) -> f64 {
+        let mut y = 0.;
+        let mut node = self.root;
+        let mut idx = idx;
+        while let Some(n) = node {
+            let Node {
+                height,
+                left,
+                right,
+                ..
+            } = self.nodes[n];
+            let left_len = self.len_of(left);
+            if idx <= left_len {
+                node = left;
+            } else {
+                y += self.sum_of(left) + height;
+                idx -= left_len + 1;
+                node = right;
+            }
+        }
+        y
+    }
+
+    /// Total height of every line.
+    pub(super) fn total(&self) -> f64 {
+        self.sum_of(self.root)
+    }
+
+    /// Find the first line that ends at or below `y`.
+    pub(super) fn find(&self, y: f64) -> Option<usize> {
+        let mut idx = 0;
+        let mut remaining = y;
+        let mut node = self.root;
+        while let Some(n) = node {
+            let Node {
+                height,
+                left,
+                right,
+                ..
+            } = self.nodes[n];
+            let end = self.sum_of(left) + height;
+            if end < remaining {
+                idx += self.len_of(left) + 1;
+                remaining -= end;
+                node = right;
+            } else {
+                node = left;
+            }
+        }
+        (idx < self.len()).then_some(idx)
+    }
+
+    /// Build a subtree of `len` lines of the same `height`, in linear time.
+    fn build(&mut self, len: usize, height: f64) -> Option<usize> {
+        // The stack holds the right spine of the tree, whose nodes can still get a right child.
+        let mut spine: Vec<usize> = Vec::new();
+        for _ in 0..len {
+            let n = self.alloc(height);
+            let mut left = None;
+            while let Some(&top) = spine.last() {
+                if self.nodes[top].priority >= self.nodes[n].priority {
+                    break;
+                }
+                spine.pop();
+                self.update(top);
+                left = Some(top);
+            }
+            self.nodes[n].left = left;
+            if let Some(&top) = spine.last() {
+                self.nodes[top].right = Some(n);
+            }
+            spine.push(n);
+        }
+
+        for &n in spine.iter().rev() {
+            self.update(n);
+        }
+        spine.first().copied()
+    }
+
+    /// Split `node` into its first `idx` lines and the rest.
+    fn split(&mut self, node: Option<usize>, idx: usize) -> (Option<usize>, Option<usize>) {
+        let Some(n) = node else {
+            return (None, None);
+        };
+
+        let left_len = self.len_of(self.nodes[n].left);
+        if idx <= left_len {
+            let (left, right) = self.split(self.nodes[n].left, idx);
+            self.nodes[n].left = right;
+            self.update(n);
+            (left, Some(n))
+        } else {
+            let (left, right) = self.split(self.nodes[n].right, idx - left_len - 1);
+            self.nodes[n].right = left;
+            self.update(n);
+            (Some(n), right)
+        }
+    }
+
+    /// Join the lines of `left` followed by the lines of `right`.
+    fn merge(&mut self, left: Option<usize>, right: Option<usize>) -> Option<usize> {
+        let (l, r) = match (left, right) {
+            (Some(l), Some(r)) => (l, r),
+            (node, None) | (None, node) => return node,
+        };
+
+        if self.nodes[l].priority > self.nodes[r].priority {
+            let right = self.merge(self.nodes[l].right, Some(r));
+            self.nodes[l].right = right;
+            self.update(l);
+            Some(l)
+        } else {
+            let left = self.merge(Some(l), self.nodes[r].left);
+            self.nodes[r].left = left;
+            self.update(r);
+            Some(r)
+        }
+    }
+
+    fn alloc(&mut self, height: f64) -> usize {
+        // A linear congruential generator is random enough to keep the tree balanced.
+        self.seed = self
+            .seed
+            .wrapping_mul(1_664_525)
+            .wrapping_add(1_013_904_223);
+        let node = Node {
+            height,
+            priority: self.seed,
+            left: None,
+            right: None,
+            len: 1,
+            sum: height,
+        };
+
+        if let Some(n) = self.free.pop() {
+            self.nodes[n] = node;
+            n
+        } else {
+            self.nodes.push(node);
+            self.nodes.len() - 1
+        }
+    }
+
+    /// Free the nodes of a subtree that was split off.
+    fn remove(&mut self, node: Option<usize>) {
+        let mut stack: Vec<usize> = node.into_iter().collect();
+        while let Some(n) = stack.pop() {
+            stack.extend(self.nodes[n].left);
+            stack.extend(self.nodes[n].right);
+            self.free.push(n);
+        }
+    }
+
+    fn update(&mut self, n: usize) {
+        let Node { left, right, .. } = self.nodes[n];
+        self.nodes[n].len = self.len_of(left) + 1 + self.len_of(right);
+        self.nodes[n].sum = self.sum_of(left) + self.nodes[n].height + self.sum_of(right);
+    }
+
+    fn len_of(&self, node: Option<usize>) -> usize {
+        node.map_or(0, |n| self.nodes[n].len)
+    }
+
+    fn sum_of(&self, node: Option<usize>) -> f64 {
+        node.map_or(0., |n| self.nodes[n].sum)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{column, Error};
+#[cfg(feature = "font-metrics")]
+use unicode_width::UnicodeWidthChar;
+use wasm_bindgen::JsCast;
+use web_sys::{window, CanvasRenderingContext2d, HtmlCanvasElement};
+
+/// Measures the width of text for a [`Layout`](super::Layout).
+pub trait TextMeasurer {
+    /// Width of `text` in pixels.
+    fn width(&mut self, text: &str) -> f64;
+}
+
+impl<M: TextMeasurer + ?Sized> TextMeasurer for Box<M> {
+    fn width(&mut self, text: &str) -> f64 {
+        (**self).width(text)
+    }
+}
+
+/// Measures text as a grid of cells with a fixed advance, where wide characters like CJK take up two cells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Monospace {
+    /// Width of one cell in pixels.
+    pub advance: f64,
+}
+
+impl Monospace {
+    /// Estimate the advance of a monospace font from its size.
+    pub fn new(font_size: f64) -> Self {
+        Self::with_advance(font_size * 0.6)
+    }
+
+    pub fn with_advance(advance: f64) -> Self {
+        Self { advance }
+    }
+}
+
+impl TextMeasurer for Monospace {
+    fn width(&mut self, text: &str) -> f64 {
+        self.advance * column::grapheme_width(text) as f64
+    }
+}
+
+/// Measures text with a 2D canvas, using the fonts available to the browser.
+pub struct Canvas {
+    context: CanvasRenderingContext2d,
+    fallback: Monospace,
+}
+
+impl Canvas {
+    /// Create a canvas that measures a monospace font of `font_size`.
+    pub fn try_new(font_size: f64) -> Result<Self, Error> {
+        let document = window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| Error::Layout("no document is available".to_owned()))?;
+        let canvas: HtmlCanvasElement = document
+            .create_element("canvas")
+            .map_err(|_| Error::Layout("failed to create a canvas".to_owned()))?
+            .unchecked_into();
+
+        let context: CanvasRenderingContext2d = canvas
+            .get_context("2d")
+            .ok()
+            .flatten()
+            .ok_or_else(|| Error::Layout("no 2D canvas context is available".to_owned()))?
+            .unchecked_into();
+        context.set_font(&format!("{}px monospace", font_size));
+
+        Ok(Self {
+            context,
+            fallback: Monospace::new(font_size),
+        })
+    }
+}
+
+impl TextMeasurer for Canvas {
+    fn width(&mut self, text: &str) -> f64 {
+        match self.context.measure_text(text) {
+            Ok(text_metrics) => text_metrics.width(),
+            Err(_) => self.fallback.width(text),
+        }
+    }
+}
+
+/// Measures text with the horizontal advances of a TrueType or OpenType font file.
+///
+/// Kerning and shaping aren't applied, so this is best suited to monospace fonts.
+#[cfg(feature = "font-metrics")]
+pub struct FontMetrics<'a> {
+    face: ttf_parser::Face<'a>,
+    scale: f64,
+}
+
+#[cfg(feature = "font-metrics")]
+impl<'a> FontMetrics<'a> {
+    /// Parse the font file in `data` to measure text of `font_size`.
+    pub fn parse(data: &'a [u8], font_size: f64) -> Result<Self, Error> {
+        let face = ttf_parser::Face::parse(data, 0)
+            .map_err(|error| Error::Layout(format!("invalid font: {error}")))?;
+        let scale = font_size / face.units_per_em() as f64;
+        Ok(Self { face, scale })
+    }
+}
+
+#[cfg(feature = "font-metrics")]
+impl TextMeasurer for FontMetrics<'_> {
+    fn width(&mut self, text: &str) -> f64 {
+        let units: u32 = text
+            .chars()
+            // Combining marks are drawn over the character before them.
+            .filter(|c| c.width() != Some(0))
+            .map(|c| {
+                // Characters missing from the font are drawn with its `.notdef` glyph.
+                let glyph = self.face.glyph_index(c).unwrap_or_default();
+                self.face.glyph_hor_advance(glyph).unwrap_or_default() as u32
+            })
+            .sum();
+        units as f64 * self.scale
+    }
+}
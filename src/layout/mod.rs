@@ -0,0 +1,433 @@
+use crate::{Buffer, Range};
+use ropey::RopeSlice;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+};
+use tree_sitter_c2rust::{InputEdit, Point};
+use unicode_segmentation::UnicodeSegmentation;
+
+mod heights;
+use heights::Heights;
+
+mod measure;
+#[cfg(feature = "font-metrics")]
+pub use measure::FontMetrics;
+pub use measure::{Canvas, Monospace, TextMeasurer};
+
+/// Character of a line, positioned relative to the top of the line.
+///
+/// Grapheme clusters are measured together, so the first character of a cluster has the width of the whole cluster
+/// and the characters after it are placed at its end without a width of their own.
+#[derive(Clone)]
+pub struct Char {
+    pub c: char,
+    pub width: f64,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Clone)]
+pub struct Line {
+    pub chars: Vec<Char>,
+    pub height: f64,
+
+    /// Indices of the characters that start a wrapped row, after the first row.
+    pub breaks: Vec<usize>,
+
+    /// Horizontal position of the wrapped rows, to line them up with the indentation of the first row.
+    pub indent: f64,
+}
+
+impl Line {
+    /// Width of this line, including its line ending.
+    pub fn width(&self) -> f64 {
+        self.chars.last().map(|c| c.x + c.width).unwrap_or_default()
+    }
+
+    /// Horizontal position of the character at `column`, or the end of the line past its last character.
+    pub fn x(&self, column: usize) -> f64 {
+        self.pos(column)[0]
+    }
+
+    /// Position of the character at `column`, or the end of the line past its last character.
+    pub fn pos(&self, column: usize) -> [f64; 2] {
+        match self.chars.get(column).or(self.chars.last()) {
+            Some(c) if column < self.chars.len() => [c.x, c.y],
+            Some(c) => [c.x + c.width, c.y],
+            None => [0., 0.],
+        }
+    }
+
+    /// Number of rows this line is wrapped into.
+    pub fn rows(&self) -> usize {
+        self.breaks.len() + 1
+    }
+
+    /// Range of the characters on the wrapped `row`.
+    pub fn row_chars(&self, row: usize) -> std::ops::Range<usize> {
+        let start = if row == 0 { 0 } else { self.breaks[row - 1] };
+        let end = self.breaks.get(row).copied().unwrap_or(self.chars.len());
+        start..end
+    }
+
+    /// Wrapped row of the character at `column`.
+    pub fn row(&self, column: usize) -> usize {
+        self.breaks.partition_point(|&start| start <= column)
+    }
+
+    /// Column of the end of the text, before the line ending.
+    pub fn end(&self) -> usize {
+        let line_ending = match self.chars.as_slice() {
+            [.., a, b] if a.c == '\r' && b.c == '\n' => 2,
+            [.., c] if is_line_ending(c.c) => 1,
+            _ => 0,
+        };
+        self.chars.len() - line_ending
+    }
+
+    /// Column of the character boundary closest to `x` on the wrapped `row`.
+    ///
+    /// Past the end of the last row this is the end of the text,
+    /// and past the end of a wrapped row it's before the character the row wraps after.
+    pub fn column(&self, row: usize, x: f64) -> usize {
+        let chars = self.row_chars(row);
+        let end = if row + 1 < self.rows() {
+            chars.end - 1
+        } else {
+            self.end()
+        }
+        .max(chars.start);
+
+        let mut column =
+            chars.start + self.chars[chars.start..end].partition_point(|c| c.x + c.width / 2. <= x);
+
+        // Characters without a width belong to the grapheme cluster before them.
+        while column > chars.start && column < end && self.chars[column].width == 0. {
+            column += 1;
+        }
+        column
+    }
+}
+
+/// Number of measured lines to keep around the visible lines.
+const MARGIN: usize = 100;
+
+/// Positions of the characters of a buffer, measured with a [`TextMeasurer`].
+///
+/// Lines are only measured when they're visible, and are one row high until then.
+pub struct Layout<M> {
+    lines: BTreeMap<usize, Line>,
+    heights: Heights,
+    version: Option<usize>,
+    measurer: M,
+    grapheme_widths: HashMap<String, f64>,
+    line_height: f64,
+    tab_width: usize,
+    wrap_width: Option<f64>,
+}
+
+impl<M: TextMeasurer> Layout<M> {
+    /// Create a layout that measures text with `measurer`.
+    pub fn new(measurer: M, line_height: f64) -> Self {
+        Self {
+            lines: BTreeMap::new(),
+            heights: Heights::default(),
+            version: None,
+            measurer,
+            grapheme_widths: HashMap::new(),
+            line_height,
+            tab_width: 4,
+            wrap_width: None,
+        }
+    }
+
+    /// Expand tabs to the next multiple of `tab_width` spaces.
+    ///
+    /// Changing the tab width forgets every measured line.
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        let tab_width = tab_width.max(1);
+        if self.tab_width != tab_width {
+            self.tab_width = tab_width;
+            self.reset(self.heights.len());
+        }
+    }
+
+    /// Wrap lines wider than `width` at word boundaries, or don't wrap lines if it's `None`.
+    ///
+    /// Changing the wrap width forgets every measured line.
+    pub fn set_wrap_width(&mut self, width: Option<f64>) {
+        if self.wrap_width != width {
+            self.wrap_width = width;
+            self.reset(self.heights.len());
+        }
+    }
+
+    /// Forget every measured line of a text with `len` lines.
+    pub fn reset(&mut self, len: usize) {
+        self.lines.clear();
+        self.heights = Heights::new(len, self.line_height);
+    }
+
+    /// Forget the lines changed by `edit`, moving the lines after it.
+    pub fn edit(&mut self, edit: &InputEdit) {
+        let start = edit.start_position.row.min(self.heights.len());
+        let end = (edit.old_end_position.row + 1)
+            .min(self.heights.len())
+            .max(start);
+        let new_len = edit.new_end_position.row + 1 - edit.start_position.row;
+        self.heights.splice(start..end, new_len, self.line_height);
+
+        let moved = self.lines.split_off(&start);
+        self.lines.extend(
+            moved
+                .into_iter()
+                .filter(|(row, _)| *row >= end)
+                .map(|(row, line)| (row - end + start + new_len, line)),
+        );
+    }
+
+    /// Apply the edits to `buffer` since the last update, or forget every line if it was more than one version ago.
+    pub fn update(&mut self, buffer: &Buffer) {
+        if self.version == Some(buffer.version()) {
+            return;
+        }
+
+        if self.version.map(|version| version + 1) == Some(buffer.version()) {
+            for edit in buffer.edits() {
+                self.edit(edit);
+            }
+        } else {
+            self.reset(buffer.rope.len_lines());
+        }
+        if self.heights.len() != buffer.rope.len_lines() {
+            self.reset(buffer.rope.len_lines());
+        }
+        self.version = Some(buffer.version());
+    }
+
+    /// Measure the lines of `text` in `rows` that haven't been measured yet.
+    pub fn measure(&mut self, text: RopeSlice, rows: std::ops::Range<usize>) {
+        for row in rows.start..rows.end.min(self.heights.len()) {
+            if self.lines.contains_key(&row) {
+                continue;
+            }
+            let line = self.measure_line(text.line(row));
+            self.heights.set(row, line.height);
+            self.lines.insert(row, line);
+        }
+    }
+
+    /// Measure the lines of `text` between `top` and `bottom`, returning their rows.
+    ///
+    /// Measured lines far from these rows are forgotten, keeping their heights.
+    pub fn measure_visible(
+        &mut self,
+        text: RopeSlice,
+        top: f64,
+        bottom: f64,
+    ) -> std::ops::Range<usize> {
+        loop {
+            // Measuring a line can change its height, and so which lines are visible.
+            let end = self.line(bottom).map_or(self.heights.len(), |row| row + 1);
+            let rows = self.line(top).unwrap_or(end).min(end)..end;
+            if rows.clone().all(|row| self.lines.contains_key(&row)) {
+                self.lines
+                    .retain(|row, _| row + MARGIN >= rows.start && *row < rows.end + MARGIN);
+                return rows;
+            }
+            self.measure(text, rows);
+        }
+    }
+
+    /// Measure the characters of a line, wrapping it into rows if it's wider than the wrap width.
+    fn measure_line(&mut self, text: RopeSlice) -> Line {
+        let text = Cow::<str>::from(text);
+        let tab_stop = self.tab_width as f64 * self.grapheme_width(" ");
+        let mut widths = Vec::with_capacity(text.len());
+        let mut x = 0.;
+        for grapheme in text.graphemes(true) {
+            let width = if grapheme == "\t" {
+                tab_stop - x % tab_stop
+            } else {
+                self.grapheme_width(grapheme)
+            };
+            x += width;
+
+            let mut chars = grapheme.chars();
+            widths.extend(chars.next().map(|c| (c, width)));
+            widths.extend(chars.map(|c| (c, 0.)));
+        }
+
+        // Wrapped rows line up with the indentation, unless it leaves too little room for the text.
+        let indent_len = widths
+            .iter()
+            .take_while(|(c, _)| *c == ' ' || *c == '\t')
+            .count();
+        let indent = widths[..indent_len]
+            .iter()
+            .fold(0., |indent, (_, width)| indent + width);
+        let indent = match self.wrap_width {
+            Some(wrap_width) if indent <= wrap_width / 2. => indent,
+            _ => 0.,
+        };
+
+        let mut chars: Vec<Char> = Vec::with_capacity(widths.len());
+        let mut breaks = Vec::new();
+        let mut word_start = None;
+        let mut x = 0.;
+        let mut row_y = 0.;
+        for (idx, (c, width)) in widths.into_iter().enumerate() {
+            let row_start = breaks.last().copied().unwrap_or_default();
+            let is_overflowing = self
+                .wrap_width
+                .is_some_and(|wrap_width| x + width > wrap_width);
+            if is_overflowing && idx > row_start && width > 0. && !c.is_whitespace() {
+                // Move the word that doesn't fit to the next row, or break it if it fills the row.
+                let start = word_start.filter(|&start| start > row_start).unwrap_or(idx);
+                breaks.push(start);
+                row_y += self.line_height;
+                x = indent;
+                for c in &mut chars[start..] {
+                    c.x = x;
+                    c.y = row_y;
+                    x += c.width;
+                }
+            }
+
+            if c.is_whitespace() && idx >= indent_len {
+                word_start = Some(idx + 1);
+            }
+            chars.push(Char {
+                c,
+                width,
+                x,
+                y: row_y,
+            });
+            x += width;
+        }
+
+        Line {
+            chars,
+            height: (breaks.len() + 1) as f64 * self.line_height,
+            breaks,
+            indent,
+        }
+    }
+
+    /// Width of a grapheme cluster, measured once and then cached.
+    ///
+    /// Line endings are as wide as a space, so selecting them is visible.
+    fn grapheme_width(&mut self, grapheme: &str) -> f64 {
+        if let Some(width) = self.grapheme_widths.get(grapheme) {
+            return *width;
+        }
+        let text = if grapheme.starts_with(['\r', '\n']) {
+            " "
+        } else {
+            grapheme
+        };
+        let width = self.measurer.width(text);
+        self.grapheme_widths.insert(grapheme.to_owned(), width);
+        width
+    }
+
+    /// Measured line at `row`.
+    pub fn get(&self, row: usize) -> Option<&Line> {
+        self.lines.get(&row)
+    }
+
+    /// Vertical position of the line at `row`.
+    pub fn y(&self, row: usize) -> f64 {
+        self.heights.y(row)
+    }
+
+    /// Height of every line.
+    pub fn height(&self) -> f64 {
+        self.heights.total()
+    }
+
+    /// Position of `point`, if its line is measured.
+    pub fn pos(&self, point: Point) -> Option<[f64; 2]> {
+        let line = self.lines.get(&point.row)?;
+        let [x, y] = line.pos(point.column);
+        Some([x, self.y(point.row) + y])
+    }
+
+    /// Rectangles as `[x, y, width, height]` covering the text in `range` on each of the given `rows`.
+    ///
+    /// Lines wrapped into more than one row have a rectangle for each row.
+    pub fn rects(&self, range: Range, rows: std::ops::Range<usize>) -> Vec<[f64; 4]> {
+        let range = range.sorted();
+        let rows = rows.start.max(range.start.row)..rows.end.min(range.end.row + 1);
+
+        let mut rects = Vec::new();
+        for row in rows {
+            let Some(line) = self.lines.get(&row) else {
+                continue;
+            };
+            let line_y = self.y(row);
+            let start = if row == range.start.row {
+                range.start.column
+            } else {
+                0
+            };
+            let end = if row == range.end.row {
+                range.end.column
+            } else {
+                line.chars.len()
+            };
+
+            let last_row = if end > start {
+                line.row(end - 1)
+            } else {
+                line.row(start)
+            };
+            for wrapped_row in line.row(start)..=last_row {
+                let chars = line.row_chars(wrapped_row);
+                let [left, y] = if start > chars.start {
+                    line.pos(start)
+                } else {
+                    line.pos(chars.start)
+                };
+                let right = if end < chars.end {
+                    line.x(end)
+                } else if chars.is_empty() {
+                    left
+                } else {
+                    let last = &line.chars[chars.end - 1];
+                    last.x + last.width
+                };
+                rects.push([left, line_y + y, right - left, self.line_height]);
+            }
+        }
+        rects
+    }
+
+    /// Row of the line at `y`, or `None` if it's below the last line.
+    pub fn line(&self, y: f64) -> Option<usize> {
+        self.heights.find(y)
+    }
+
+    /// Point closest to `x` and `y`, or the end of the text below the last line.
+    ///
+    /// This is `None` if the line at `y` isn't measured.
+    pub fn target(&self, x: f64, y: f64) -> Option<Point> {
+        let Some(row) = self.line(y) else {
+            let row = self.heights.len().checked_sub(1)?;
+            return Some(Point::new(row, self.lines.get(&row)?.end()));
+        };
+        let line = self.lines.get(&row)?;
+
+        let wrapped_row =
+            (((y - self.y(row)) / self.line_height).max(0.) as usize).min(line.rows() - 1);
+        Some(Point::new(row, line.column(wrapped_row, x)))
+    }
+}
+
+fn is_line_ending(c: char) -> bool {
+    matches!(
+        c,
+        '\n' | '\r' | '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}'
+    )
+}
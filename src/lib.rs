@@ -1,33 +1,115 @@
 mod buffer;
-pub use buffer::{use_buffer, Buffer};
+pub use buffer::{try_use_buffer, use_buffer, Buffer, RopeProvider};
+
+pub mod clipboard;
+pub use clipboard::Clipboard;
+
+pub mod column;
 
 mod editor;
-pub use editor::{Builder, Editor, UseEditor};
+pub use editor::{
+    structure, Builder, Command, CommandTask, Commands, Editor, KeyChord, Keymap, Lookup, Motion,
+    ParseKeyError, Register, UseEditor, UseVim, VimAction, VimMode, VimParse,
+};
+
+mod error;
+pub use error::Error;
 
 pub mod language;
-pub use language::{use_language, Language};
+pub use language::{use_language, Language, LanguageRegistry};
+
+mod injection;
+pub use injection::InjectionLayer;
+
+mod history;
+pub use history::{Change, EditKind, History, Transaction};
+
+pub mod layout;
+pub use layout::{Layout, TextMeasurer};
 
-mod layout;
+mod predicate;
+pub use predicate::{PredicateArg, Predicates};
+
+pub mod search;
+pub use search::{SearchError, SearchMode, SearchQuery};
 
 mod span;
 pub use span::Span;
 
+pub mod theme;
+pub use theme::{Style, Theme};
+
 mod use_highlights;
-pub use use_highlights::{use_highlights, Highlight};
+pub use use_highlights::{use_highlights, Highlight, HighlightCache, HighlightIndex};
 
 mod use_query;
 pub use use_query::{use_query, use_query_signal};
 
 pub use tree_sitter_c2rust::Point;
 
+/// Range of text between two points.
+///
+/// The column of each point is the index of a character (`char`) in its line,
+/// unlike tree-sitter points which have byte columns. See [`column`] to convert between them.
+///
+/// Ranges are equal if they have the same points, whatever their goal columns.
 #[derive(Clone, Copy, Default, Debug)]
 pub struct Range {
     pub start: Point,
     pub end: Point,
+
+    /// Display column the cursor tries to stay on when moving between lines,
+    /// see [`column::char_to_display`].
+    pub goal_column: Option<usize>,
 }
 
 impl Range {
     pub fn new(start: Point, end: Point) -> Self {
-        Self { start, end }
+        Self {
+            start,
+            end,
+            goal_column: None,
+        }
+    }
+
+    /// Create an empty range at `point`.
+    pub fn point(point: Point) -> Self {
+        Self::new(point, point)
+    }
+
+    /// Returns `true` if this range doesn't contain any text.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns this range with `start` before `end`, keeping its goal column.
+    pub fn sorted(self) -> Self {
+        if self.end < self.start {
+            Self {
+                start: self.end,
+                end: self.start,
+                ..self
+            }
+        } else {
+            self
+        }
+    }
+
+    /// Position of the cursor, which moves when the selection is extended.
+    pub fn head(&self) -> Point {
+        self.end
+    }
+
+    /// Position where the selection started, which stays fixed when it is extended.
+    pub fn anchor(&self) -> Point {
+        self.start
     }
 }
+
+impl PartialEq for Range {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start && self.end == other.end
+    }
+}
+
+impl Eq for Range {}
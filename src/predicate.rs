@@ -0,0 +1,95 @@
+use ropey::RopeSlice;
+use std::{borrow::Cow, collections::HashMap, rc::Rc};
+use tree_sitter_c2rust::{Query, QueryMatch, QueryPredicateArg};
+
+/// Argument of a query predicate, like `@name` or `"self"` in `(#any-of? @name "self" "Self")`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PredicateArg<'a> {
+    /// Text of the first node of a capture.
+    Capture(Cow<'a, str>),
+    String(&'a str),
+}
+
+type Handler = Rc<dyn Fn(&[PredicateArg]) -> bool>;
+
+/// Registry of the predicates that filter query matches, like `#any-of?`.
+///
+/// Tree-sitter checks `#eq?` and `#match?` itself, so these are the predicates it leaves to the host.
+/// The [`Default`] registry contains `#any-of?` and `#not-any-of?`.
+#[derive(Clone)]
+pub struct Predicates {
+    handlers: HashMap<String, Handler>,
+}
+
+impl Predicates {
+    /// Create a registry without any predicates.
+    pub fn empty() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Register a predicate, like `"is-upper?"`, replacing any existing predicate with the same name.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        f: impl Fn(&[PredicateArg]) -> bool + 'static,
+    ) -> &mut Self {
+        self.handlers.insert(name.into(), Rc::new(f));
+        self
+    }
+
+    /// Returns `true` if `mat` satisfies every registered predicate of its pattern in `query`.
+    ///
+    /// Predicates that aren't registered, like `#set!`, are ignored,
+    /// and so are predicates of captures that didn't match a node.
+    pub fn is_match(&self, query: &Query, mat: &QueryMatch, text: RopeSlice) -> bool {
+        query
+            .general_predicates(mat.pattern_index)
+            .iter()
+            .all(|predicate| {
+                let Some(handler) = self.handlers.get(&*predicate.operator) else {
+                    return true;
+                };
+                let args: Option<Vec<_>> = predicate
+                    .args
+                    .iter()
+                    .map(|arg| match arg {
+                        QueryPredicateArg::Capture(index) => {
+                            let node = mat.nodes_for_capture_index(*index).next()?;
+                            let len = text.len_bytes();
+                            let bytes = node.start_byte().min(len)..node.end_byte().min(len);
+                            Some(PredicateArg::Capture(text.byte_slice(bytes).into()))
+                        }
+                        QueryPredicateArg::String(value) => Some(PredicateArg::String(value)),
+                    })
+                    .collect();
+                match args {
+                    Some(args) => handler(&args),
+                    None => true,
+                }
+            })
+    }
+}
+
+impl Default for Predicates {
+    fn default() -> Self {
+        let mut predicates = Self::empty();
+        predicates
+            .register("any-of?", any_of)
+            .register("not-any-of?", |args| {
+                matches!(args, [PredicateArg::Capture(_), ..]) && !any_of(args)
+            });
+        predicates
+    }
+}
+
+/// Returns `true` if the text of the captured node is one of the strings that follow it.
+fn any_of(args: &[PredicateArg]) -> bool {
+    let [PredicateArg::Capture(text), values @ ..] = args else {
+        return false;
+    };
+    values
+        .iter()
+        .any(|value| matches!(value, PredicateArg::String(value) if *value == text.as_ref()))
+}
@@ -0,0 +1,270 @@
+use crate::{Buffer, Predicates, RopeProvider};
+use regex::{Captures, Regex, RegexBuilder};
+use ropey::RopeSlice;
+use std::{borrow::Cow, fmt, ops::Range};
+use tree_sitter_c2rust::{Query, QueryCursor, QueryError};
+
+/// How the pattern of a [`SearchQuery`] is matched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Match the pattern as plain text.
+    #[default]
+    Literal,
+
+    /// Match the pattern as a regular expression.
+    Regex,
+
+    /// Match the pattern as a tree-sitter query, like `(call_expression) @call`.
+    ///
+    /// Each match spans the nodes it captures.
+    Query,
+}
+
+/// Text to search a buffer for.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub pattern: String,
+    pub mode: SearchMode,
+    pub is_case_sensitive: bool,
+    pub is_whole_word: bool,
+}
+
+impl SearchQuery {
+    /// Create a case-insensitive literal query for `pattern`.
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn mode(mut self, mode: SearchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn case_sensitive(mut self, is_case_sensitive: bool) -> Self {
+        self.is_case_sensitive = is_case_sensitive;
+        self
+    }
+
+    pub fn whole_word(mut self, is_whole_word: bool) -> Self {
+        self.is_whole_word = is_whole_word;
+        self
+    }
+
+    /// Find the character ranges of every match in `buffer`.
+    ///
+    /// Matches of a [`SearchMode::Query`] are filtered by `predicates`, like highlights.
+    pub fn find(
+        &self,
+        buffer: &Buffer,
+        predicates: &Predicates,
+    ) -> Result<Vec<Range<usize>>, SearchError> {
+        if self.pattern.is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.mode == SearchMode::Query {
+            return self.find_nodes(buffer, predicates);
+        }
+
+        let regex = self.to_regex()?;
+        let mut matches = Vec::new();
+        self.each_match(&regex, buffer.rope.slice(..), |chars, _| {
+            matches.push(chars)
+        });
+        Ok(matches)
+    }
+
+    /// Find every match in `buffer` along with the text it's replaced with.
+    ///
+    /// In [`SearchMode::Regex`] the replacement can refer to capture groups, like `$1`.
+    pub fn replacements(
+        &self,
+        buffer: &Buffer,
+        predicates: &Predicates,
+        replacement: &str,
+    ) -> Result<Vec<(Range<usize>, String)>, SearchError> {
+        if self.pattern.is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.mode == SearchMode::Query {
+            let matches = self.find_nodes(buffer, predicates)?;
+            return Ok(matches
+                .into_iter()
+                .map(|chars| (chars, replacement.to_owned()))
+                .collect());
+        }
+
+        let regex = self.to_regex()?;
+        let mut replacements = Vec::new();
+        self.each_match(&regex, buffer.rope.slice(..), |chars, captures| {
+            let mut new_text = String::new();
+            if self.mode == SearchMode::Regex {
+                captures.expand(replacement, &mut new_text);
+            } else {
+                new_text.push_str(replacement);
+            }
+            replacements.push((chars, new_text));
+        });
+        Ok(replacements)
+    }
+
+    /// Call `f` with the character range and captures of every non-empty match of `regex` in `rope`.
+    ///
+    /// Lines are searched one at a time, so only lines that span chunks are copied.
+    /// Patterns with a line break search the whole text instead.
+    fn each_match(
+        &self,
+        regex: &Regex,
+        rope: RopeSlice,
+        mut f: impl FnMut(Range<usize>, Captures),
+    ) {
+        let is_multi_line = match self.mode {
+            SearchMode::Regex => self.pattern.contains('\n') || self.pattern.contains(r"\n"),
+            SearchMode::Literal | SearchMode::Query => self.pattern.contains('\n'),
+        };
+        let lines: Box<dyn Iterator<Item = (usize, RopeSlice)>> = if is_multi_line {
+            Box::new(std::iter::once((0, rope)))
+        } else {
+            Box::new(
+                rope.lines()
+                    .enumerate()
+                    .map(|(row, line)| (rope.line_to_char(row), line)),
+            )
+        };
+
+        for (line_char, line) in lines {
+            let text: Cow<str> = line.into();
+            let mut byte_idx = 0;
+            while let Some(captures) = regex.captures_at(&text, byte_idx) {
+                let m = captures.get(0).unwrap();
+                if m.is_empty() || (self.is_whole_word && !is_whole_word(&text, m.range())) {
+                    // Try again from the next character, since a later start could still match.
+                    match text[m.start()..].chars().next() {
+                        Some(c) => byte_idx = m.start() + c.len_utf8(),
+                        None => break,
+                    }
+                    continue;
+                }
+
+                byte_idx = m.end();
+                let chars = line_char + line.byte_to_char(m.start())
+                    ..line_char + line.byte_to_char(m.end());
+                f(chars, captures);
+            }
+        }
+    }
+
+    /// Find the character ranges spanned by the captures of every match of a tree-sitter query.
+    fn find_nodes(
+        &self,
+        buffer: &Buffer,
+        predicates: &Predicates,
+    ) -> Result<Vec<Range<usize>>, SearchError> {
+        let query =
+            Query::new(buffer.tree.language(), &self.pattern).map_err(SearchError::Query)?;
+        if query.capture_names().is_empty() {
+            return Err(SearchError::NoCaptures);
+        }
+
+        let text = buffer.rope.slice(..);
+        let mut query_cursor = QueryCursor::new();
+        let mut matches: Vec<_> = query_cursor
+            .matches(
+                &query,
+                buffer.tree.root_node(),
+                RopeProvider { slice: text },
+            )
+            .filter(|mat| predicates.is_match(&query, mat, text))
+            .filter_map(|mat| {
+                let start = mat.captures.iter().map(|c| c.node.start_byte()).min()?;
+                let end = mat.captures.iter().map(|c| c.node.end_byte()).max()?;
+                Some(buffer.rope.byte_to_char(start)..buffer.rope.byte_to_char(end))
+            })
+            .filter(|chars| chars.start < chars.end)
+            .collect();
+        matches.sort_by_key(|chars| (chars.start, chars.end));
+        matches.dedup();
+        Ok(matches)
+    }
+
+    fn to_regex(&self) -> Result<Regex, SearchError> {
+        let pattern = match self.mode {
+            SearchMode::Regex => self.pattern.clone(),
+            SearchMode::Literal | SearchMode::Query => regex::escape(&self.pattern),
+        };
+
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.is_case_sensitive)
+            .multi_line(true)
+            .build()
+            .map_err(SearchError::Regex)
+    }
+}
+
+/// Check if `range` of `text` is a whole word.
+///
+/// Each end must be at the edge of the text, next to a non-word character, or be a non-word character itself,
+/// so patterns like `.foo` or `x+` can still match whole words.
+fn is_whole_word(text: &str, range: Range<usize>) -> bool {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let matched = &text[range.clone()];
+    let is_start =
+        !is_word(text[..range.start].chars().next_back()) || !is_word(matched.chars().next());
+    let is_end =
+        !is_word(text[range.end..].chars().next()) || !is_word(matched.chars().next_back());
+    is_start && is_end
+}
+
+/// Find the byte index of the first occurrence of `needle` in `text` that starts at or after `byte_idx`.
+///
+/// The text is searched one chunk at a time, so it's never copied into a single string.
+pub fn find_in_rope(text: RopeSlice, needle: &str, byte_idx: usize) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(byte_idx);
+    }
+
+    // The window holds the end of the previous chunks, which could be the start of a match, and the current chunk.
+    let mut window = String::new();
+    let mut window_start = byte_idx;
+    for chunk in text.byte_slice(byte_idx..).chunks() {
+        window.push_str(chunk);
+        if let Some(idx) = window.find(needle) {
+            return Some(window_start + idx);
+        }
+
+        let mut end = window.len().saturating_sub(needle.len() - 1);
+        while !window.is_char_boundary(end) {
+            end -= 1;
+        }
+        window.drain(..end);
+        window_start += end;
+    }
+    None
+}
+
+/// Error returned when a [`SearchQuery`] is invalid.
+#[derive(Debug)]
+pub enum SearchError {
+    /// The pattern isn't a valid regular expression.
+    Regex(regex::Error),
+
+    /// The pattern isn't a valid tree-sitter query for the buffer's language.
+    Query(QueryError),
+
+    /// The tree-sitter query doesn't capture any nodes to match.
+    NoCaptures,
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Regex(error) => error.fmt(f),
+            Self::Query(error) => error.fmt(f),
+            Self::NoCaptures => f.write_str("query must capture a node, like `(identifier) @name`"),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
@@ -1,6 +1,6 @@
 use std::rc::Rc;
 
-/// Span of text with an optional node kind.
+/// Span of text with an optional highlight name, like `"keyword"`.
 #[derive(Clone, Debug, Eq)]
 pub struct Span {
     pub kind: Option<Rc<str>>,
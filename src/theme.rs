@@ -0,0 +1,421 @@
+use crate::Error;
+use serde_json::{Map, Value};
+
+/// Style of highlighted text.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    /// CSS color of the text, like `"#cf222e"`.
+    pub color: Option<String>,
+    pub is_bold: bool,
+    pub is_italic: bool,
+    pub is_underline: bool,
+}
+
+impl Style {
+    /// Create a style with the text color `color`.
+    pub fn new(color: impl Into<String>) -> Self {
+        Self {
+            color: Some(color.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn bold(mut self, is_bold: bool) -> Self {
+        self.is_bold = is_bold;
+        self
+    }
+
+    pub fn italic(mut self, is_italic: bool) -> Self {
+        self.is_italic = is_italic;
+        self
+    }
+
+    pub fn underline(mut self, is_underline: bool) -> Self {
+        self.is_underline = is_underline;
+        self
+    }
+}
+
+/// Colors of an editor and the syntax it highlights.
+///
+/// Syntax is styled by the capture names of highlight queries, like `"keyword"` or `"function.method"`.
+/// Colors are CSS colors, like `"#ffffff"` or `"rgba(84, 174, 255, 0.3)"`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+
+    /// Color of text without a style.
+    pub foreground: String,
+    pub background: String,
+    pub border: String,
+
+    /// Background of the line numbers.
+    pub gutter: String,
+    pub line_number: String,
+
+    /// Color of the line number of the line with a cursor.
+    pub current_line_number: String,
+
+    /// Border of the line with a cursor.
+    pub current_line: String,
+    pub selection: String,
+    pub search_match: String,
+    pub cursor: String,
+
+    /// Background of panels over the text, like the search panel.
+    pub panel: String,
+    pub panel_border: String,
+
+    /// Background of the enabled options of a panel, like "Match case".
+    pub panel_active: String,
+
+    /// Color of error messages.
+    pub error: String,
+    pub error_background: String,
+    pub error_border: String,
+    styles: Vec<(String, Style)>,
+}
+
+impl Theme {
+    /// Bundled light theme.
+    pub fn light() -> Self {
+        let mut theme = Self {
+            name: "Light".to_owned(),
+            foreground: "#000".to_owned(),
+            background: "#fff".to_owned(),
+            border: "#ccc".to_owned(),
+            gutter: "transparent".to_owned(),
+            line_number: "#888".to_owned(),
+            current_line_number: "#000".to_owned(),
+            current_line: "#c6cdd5".to_owned(),
+            selection: "rgba(84, 174, 255, 0.3)".to_owned(),
+            search_match: "rgba(255, 200, 0, 0.4)".to_owned(),
+            cursor: "#000".to_owned(),
+            panel: "#f6f8fa".to_owned(),
+            panel_border: "#ccc".to_owned(),
+            panel_active: "#cce4ff".to_owned(),
+            error: "#82071e".to_owned(),
+            error_background: "#ffebe9".to_owned(),
+            error_border: "#ff8182".to_owned(),
+            styles: Vec::new(),
+        };
+        theme
+            .set_style("keyword", Style::new("#cf222e"))
+            .set_style("variable.builtin", Style::new("#cf222e"))
+            .set_style("function", Style::new("#8250df"))
+            .set_style("constructor", Style::new("#8250df"))
+            .set_style("attribute", Style::new("#8250df"))
+            .set_style("type", Style::new("#953800"))
+            .set_style("constant", Style::new("#0550ae"))
+            .set_style("number", Style::new("#0550ae"))
+            .set_style("property", Style::new("#0550ae"))
+            .set_style("string", Style::new("#0a3069"))
+            .set_style("escape", Style::new("#0a3069").bold(true))
+            .set_style("comment", Style::new("#6e7781").italic(true))
+            .set_style("operator", Style::new("#cf222e"))
+            .set_style("punctuation", Style::new("#57606a"))
+            .set_style("tag", Style::new("#116329"))
+            .set_style("label", Style::new("#953800"))
+            .set_style("text.title", Style::new("#0550ae").bold(true))
+            .set_style("text.strong", Style::default().bold(true))
+            .set_style("text.emphasis", Style::default().italic(true))
+            .set_style("text.literal", Style::new("#0a3069"))
+            .set_style("text.uri", Style::new("#0a3069").underline(true));
+        theme
+    }
+
+    /// Bundled dark theme.
+    pub fn dark() -> Self {
+        let mut theme = Self {
+            name: "Dark".to_owned(),
+            foreground: "#c9d1d9".to_owned(),
+            background: "#0d1117".to_owned(),
+            border: "#30363d".to_owned(),
+            gutter: "transparent".to_owned(),
+            line_number: "#6e7681".to_owned(),
+            current_line_number: "#c9d1d9".to_owned(),
+            current_line: "#30363d".to_owned(),
+            selection: "rgba(56, 139, 253, 0.4)".to_owned(),
+            search_match: "rgba(187, 128, 9, 0.4)".to_owned(),
+            cursor: "#c9d1d9".to_owned(),
+            panel: "#161b22".to_owned(),
+            panel_border: "#30363d".to_owned(),
+            panel_active: "rgba(56, 139, 253, 0.4)".to_owned(),
+            error: "#ffa198".to_owned(),
+            error_background: "rgba(248, 81, 73, 0.1)".to_owned(),
+            error_border: "rgba(248, 81, 73, 0.4)".to_owned(),
+            styles: Vec::new(),
+        };
+        theme
+            .set_style("keyword", Style::new("#ff7b72"))
+            .set_style("variable.builtin", Style::new("#ff7b72"))
+            .set_style("function", Style::new("#d2a8ff"))
+            .set_style("constructor", Style::new("#d2a8ff"))
+            .set_style("attribute", Style::new("#d2a8ff"))
+            .set_style("type", Style::new("#ffa657"))
+            .set_style("constant", Style::new("#79c0ff"))
+            .set_style("number", Style::new("#79c0ff"))
+            .set_style("property", Style::new("#79c0ff"))
+            .set_style("string", Style::new("#a5d6ff"))
+            .set_style("escape", Style::new("#a5d6ff").bold(true))
+            .set_style("comment", Style::new("#8b949e").italic(true))
+            .set_style("operator", Style::new("#ff7b72"))
+            .set_style("punctuation", Style::new("#8b949e"))
+            .set_style("tag", Style::new("#7ee787"))
+            .set_style("label", Style::new("#ffa657"))
+            .set_style("text.title", Style::new("#79c0ff").bold(true))
+            .set_style("text.strong", Style::default().bold(true))
+            .set_style("text.emphasis", Style::default().italic(true))
+            .set_style("text.literal", Style::new("#a5d6ff"))
+            .set_style("text.uri", Style::new("#a5d6ff").underline(true));
+        theme
+    }
+
+    /// Load a VS Code or TextMate theme from its JSON.
+    ///
+    /// Capture names of highlight queries, like `"function"`, are styled with the TextMate scope they
+    /// correspond to, like `"entity.name.function"`, unless the theme styles them directly.
+    /// Comments and trailing commas are allowed, like in VS Code theme files.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let value: Value = serde_json::from_str(&strip_jsonc(json))
+            .map_err(|error| Error::Theme(error.to_string()))?;
+        let object = value
+            .as_object()
+            .ok_or_else(|| Error::Theme("expected an object".to_owned()))?;
+
+        let mut theme = if get_str(object, "type") == Some("dark") {
+            Self::dark()
+        } else {
+            Self::light()
+        };
+        theme.styles.clear();
+        if let Some(name) = get_str(object, "name") {
+            theme.name = name.to_owned();
+        }
+
+        // VS Code themes set UI colors in `colors` and syntax styles in `tokenColors`,
+        // while TextMate themes set both in `settings`, with the UI colors in the rule without a scope.
+        let rules = match (object.get("tokenColors"), object.get("settings")) {
+            (Some(rules), _) | (None, Some(rules)) => rules
+                .as_array()
+                .ok_or_else(|| Error::Theme("expected an array of token colors".to_owned()))?,
+            (None, None) => return Err(Error::Theme("missing token colors".to_owned())),
+        };
+        if let Some(Value::Object(colors)) = object.get("colors") {
+            theme.set_colors(colors, Self::vs_code_color);
+        }
+
+        for rule in rules {
+            let Some(settings) = rule.get("settings").and_then(Value::as_object) else {
+                continue;
+            };
+            let scopes: Vec<&str> = match rule.get("scope") {
+                Some(Value::String(scopes)) => scopes.split(',').collect(),
+                Some(Value::Array(scopes)) => scopes.iter().filter_map(Value::as_str).collect(),
+                _ => {
+                    theme.set_colors(settings, Self::textmate_color);
+                    continue;
+                }
+            };
+
+            let font_style = get_str(settings, "fontStyle").unwrap_or_default();
+            let style = Style {
+                color: get_str(settings, "foreground").map(str::to_owned),
+                is_bold: font_style.contains("bold"),
+                is_italic: font_style.contains("italic"),
+                is_underline: font_style.contains("underline"),
+            };
+            for scope in scopes {
+                // Only the last part of a descendant selector like `source.rust keyword` is matched.
+                if let Some(scope) = scope.split_whitespace().last() {
+                    theme.set_style(scope, style.clone());
+                }
+            }
+        }
+
+        for (name, scope) in CAPTURE_SCOPES {
+            if theme.styles.iter().all(|(styled, _)| styled != name) {
+                if let Some(style) = theme.style(scope).cloned() {
+                    theme.set_style(*name, style);
+                }
+            }
+        }
+
+        Ok(theme)
+    }
+
+    /// Style of text highlighted as `scope`.
+    ///
+    /// Scopes are hierarchical, so `"function.method"` falls back to the style of `"function"`.
+    pub fn style(&self, scope: &str) -> Option<&Style> {
+        let mut scope = scope;
+        loop {
+            if let Some((_, style)) = self.styles.iter().find(|(name, _)| name == scope) {
+                return Some(style);
+            }
+            scope = scope.rsplit_once('.')?.0;
+        }
+    }
+
+    /// Set the style of text highlighted as `scope`, replacing any existing style.
+    pub fn set_style(&mut self, scope: impl Into<String>, style: Style) -> &mut Self {
+        let scope = scope.into();
+        self.styles.retain(|(name, _)| *name != scope);
+        self.styles.push((scope, style));
+        self
+    }
+
+    /// Iterate over the syntax scopes and their styles.
+    pub fn styles(&self) -> impl Iterator<Item = (&str, &Style)> {
+        self.styles
+            .iter()
+            .map(|(scope, style)| (scope.as_str(), style))
+    }
+
+    /// Set the UI colors found in `colors`, using `color` to find the color of each key.
+    fn set_colors(
+        &mut self,
+        colors: &Map<String, Value>,
+        color: for<'a> fn(&'a mut Self, &str) -> Option<&'a mut String>,
+    ) {
+        for (key, value) in colors {
+            if let (Some(field), Some(value)) = (color(self, key), value.as_str()) {
+                *field = value.to_owned();
+            }
+        }
+    }
+
+    /// UI color of the VS Code color named `key`, like `"editor.background"`.
+    fn vs_code_color(&mut self, key: &str) -> Option<&mut String> {
+        Some(match key {
+            "editor.foreground" => &mut self.foreground,
+            "editor.background" => &mut self.background,
+            "editorGroup.border" => &mut self.border,
+            "editorGutter.background" => &mut self.gutter,
+            "editorLineNumber.foreground" => &mut self.line_number,
+            "editorLineNumber.activeForeground" => &mut self.current_line_number,
+            "editor.lineHighlightBorder" => &mut self.current_line,
+            "editor.selectionBackground" => &mut self.selection,
+            "editor.findMatchHighlightBackground" => &mut self.search_match,
+            "editorCursor.foreground" => &mut self.cursor,
+            "editorWidget.background" => &mut self.panel,
+            "editorWidget.border" => &mut self.panel_border,
+            "inputOption.activeBackground" => &mut self.panel_active,
+            "inputValidation.errorForeground" => &mut self.error,
+            "inputValidation.errorBackground" => &mut self.error_background,
+            "inputValidation.errorBorder" => &mut self.error_border,
+            _ => return None,
+        })
+    }
+
+    /// UI color of the TextMate setting named `key`, like `"caret"`.
+    fn textmate_color(&mut self, key: &str) -> Option<&mut String> {
+        Some(match key {
+            "foreground" => &mut self.foreground,
+            "background" => &mut self.background,
+            "gutter" => &mut self.gutter,
+            "gutterForeground" => &mut self.line_number,
+            "selection" => &mut self.selection,
+            "findHighlight" => &mut self.search_match,
+            "caret" => &mut self.cursor,
+            _ => return None,
+        })
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+/// TextMate scopes used to style the capture names of highlight queries.
+const CAPTURE_SCOPES: &[(&str, &str)] = &[
+    ("attribute", "entity.other.attribute-name"),
+    ("comment", "comment"),
+    ("constant", "constant"),
+    ("constant.builtin", "constant.language"),
+    ("constructor", "entity.name.function"),
+    ("escape", "constant.character.escape"),
+    ("function", "entity.name.function"),
+    ("function.builtin", "support.function"),
+    ("function.macro", "entity.name.function.macro"),
+    ("keyword", "keyword"),
+    ("label", "entity.name.label"),
+    ("number", "constant.numeric"),
+    ("operator", "keyword.operator"),
+    ("property", "variable.other.property"),
+    ("punctuation", "punctuation"),
+    ("string", "string"),
+    ("string.special", "string.regexp"),
+    ("tag", "entity.name.tag"),
+    ("text.emphasis", "markup.italic"),
+    ("text.literal", "markup.inline.raw"),
+    ("text.strong", "markup.bold"),
+    ("text.title", "markup.heading"),
+    ("text.uri", "markup.underline.link"),
+    ("type", "entity.name.type"),
+    ("type.builtin", "support.type"),
+    ("variable", "variable"),
+    ("variable.builtin", "variable.language"),
+    ("variable.parameter", "variable.parameter"),
+];
+
+fn get_str<'a>(object: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    object.get(key).and_then(Value::as_str)
+}
+
+/// Remove the comments and trailing commas of JSON with comments, like VS Code settings.
+fn strip_jsonc(json: &str) -> String {
+    let mut output = String::with_capacity(json.len());
+    let mut chars = json.chars().peekable();
+    let mut is_string = false;
+
+    while let Some(c) = chars.next() {
+        if is_string {
+            output.push(c);
+            match c {
+                '\\' => output.extend(chars.next()),
+                '"' => is_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                is_string = true;
+                output.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        output.push(c);
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = None;
+                for c in chars.by_ref() {
+                    if last == Some('*') && c == '/' {
+                        break;
+                    }
+                    last = Some(c);
+                }
+            }
+            (']' | '}', _) => {
+                // Remove a trailing comma before the end of an array or object.
+                let len = output.trim_end().len();
+                if output[..len].ends_with(',') {
+                    output.remove(len - 1);
+                }
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
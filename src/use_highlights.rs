@@ -1,29 +1,382 @@
-use crate::{use_language, use_query, Buffer};
-use dioxus::prelude::Scope;
+use crate::{use_language, use_query_signal, Buffer, Error, Predicates, RopeProvider};
+use dioxus::prelude::{use_context, Scope};
 use dioxus_signals::{use_signal, Signal};
-use tree_sitter_c2rust::Range;
+use std::{cmp::Reverse, ops, slice};
+use tree_sitter_c2rust::{InputEdit, Point, Query, QueryCursor, QueryMatch, Range};
+
+/// Number of rows above and below the visible rows that are highlighted ahead of scrolling.
+const MARGIN: usize = 50;
 
 /// Highlighted text span.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Highlight {
-    pub kind: String,
+    /// Name of the query capture, like `"function.method"`.
+    pub name: String,
+
+    /// Index of the query pattern that captured the text, where earlier patterns take precedence.
+    pub pattern_index: usize,
     pub range: Range,
 }
 
-pub fn use_highlights<T>(cx: Scope<T>, buffer: Signal<Buffer>) -> Signal<Vec<Highlight>> {
+impl Highlight {
+    /// Create the highlights of every capture in `matches` of `query`, skipping private captures like `@_name`.
+    pub fn from_matches<'a, 'tree: 'a>(
+        query: &Query,
+        matches: impl Iterator<Item = QueryMatch<'a, 'tree>>,
+    ) -> Vec<Self> {
+        let mut highlights = Vec::new();
+        for mat in matches {
+            for capture in mat.captures {
+                let name = &query.capture_names()[capture.index as usize];
+                if !name.starts_with('_') {
+                    highlights.push(Highlight {
+                        name: name.clone(),
+                        pattern_index: mat.pattern_index,
+                        range: capture.node.range(),
+                    });
+                }
+            }
+        }
+        highlights
+    }
+}
+
+/// Highlight the visible `rows` of `buffer`, plus a margin, with the language's highlight query,
+/// including any injected languages.
+///
+/// Highlights are cached by row, so only rows that were edited or scrolled into view are queried again.
+pub fn use_highlights<T>(
+    cx: Scope<T>,
+    buffer: Signal<Buffer>,
+    rows: Signal<ops::Range<usize>>,
+) -> Signal<HighlightIndex> {
     let language = use_language(cx);
-    let highlights = use_signal(cx, || Vec::new());
-    use_query(cx, language().highlight_query, buffer, move |matches| {
-        let items = matches
-            .flat_map(|mat| {
-                mat.captures.iter().map(|capture| {
-                    let range = capture.node.range();
-                    let kind = capture.node.kind().to_owned();
-                    Highlight { range, kind }
-                })
-            })
-            .collect();
-        highlights.set(items);
+    let highlight_query = language
+        .map(|language| language().highlight_query)
+        .unwrap_or_default();
+    let query_signal = use_query_signal(cx, highlight_query);
+    let error_signal = use_context::<Signal<Option<Error>>>(cx).copied();
+    let predicates = use_context::<Signal<Predicates>>(cx).copied();
+    let cache = use_signal(cx, HighlightCache::default);
+    let highlights = use_signal(cx, HighlightIndex::default);
+
+    dioxus_signals::use_effect(cx, move || {
+        let buffer_ref = buffer();
+        let query_ref = query_signal();
+        let query = match &*query_ref {
+            Ok(query) => query,
+            Err(error) => {
+                if let Some(error_signal) = error_signal {
+                    error_signal.set(Some(error.clone()));
+                }
+                return;
+            }
+        };
+        let source = language
+            .map(|language| language().highlight_query)
+            .unwrap_or_default();
+
+        let rows = rows();
+        let end = (rows.end + MARGIN).min(buffer_ref.rope.len_lines());
+        let rows = rows.start.saturating_sub(MARGIN).min(end)..end;
+
+        let predicates = predicates
+            .map(|predicates| predicates().clone())
+            .unwrap_or_default();
+
+        let mut cache = cache.write();
+        cache.update(&buffer_ref, source);
+        cache.fill(&buffer_ref, query, &predicates, rows.clone());
+        let items = cache.highlights(rows.clone());
+        let injected = buffer_ref.injection_highlights(rows, &predicates);
+        highlights.set(HighlightIndex::new([items, injected]));
     });
     highlights
 }
+
+/// Highlights of each row of a buffer, kept in sync with its edits.
+#[derive(Default)]
+pub struct HighlightCache {
+    /// Highlight query the rows were queried with.
+    query: &'static str,
+
+    /// Version of the buffer the rows are up to date with.
+    version: Option<usize>,
+
+    /// Highlights that overlap each row, or `None` if the row hasn't been queried yet.
+    rows: Vec<Option<Vec<Highlight>>>,
+}
+
+impl HighlightCache {
+    /// Move the cached highlights to match the edits made since the last update,
+    /// and forget the rows that were edited or whose syntax changed.
+    ///
+    /// If the query changed or any versions of the buffer were missed, every row is forgotten.
+    pub fn update(&mut self, buffer: &Buffer, query: &'static str) {
+        if self.query == query && self.version == Some(buffer.version()) {
+            return;
+        }
+
+        if self.query == query && self.version.map(|version| version + 1) == Some(buffer.version())
+        {
+            for edit in buffer.edits() {
+                self.edit(edit);
+            }
+            self.rows.resize(buffer.rope.len_lines(), None);
+            for range in buffer.changed_ranges() {
+                let end = (range.end_point.row + 1).min(self.rows.len());
+                self.rows[range.start_point.row.min(end)..end].fill(None);
+            }
+        } else {
+            self.query = query;
+            self.rows.clear();
+            self.rows.resize(buffer.rope.len_lines(), None);
+        }
+        self.version = Some(buffer.version());
+    }
+
+    /// Replace the edited rows and forget any row with a highlight that touches the edit.
+    ///
+    /// This walks every cached row for each edit, to move the highlights after it.
+    fn edit(&mut self, edit: &InputEdit) {
+        let start = edit.start_position.row.min(self.rows.len());
+        let end = (edit.old_end_position.row + 1)
+            .min(self.rows.len())
+            .max(start);
+        let new_rows = edit.new_end_position.row + 1 - edit.start_position.row;
+        self.rows.splice(start..end, vec![None; new_rows]);
+
+        for row in &mut self.rows {
+            let Some(highlights) = row else {
+                continue;
+            };
+            if highlights.iter().any(|highlight| {
+                highlight.range.start_byte <= edit.old_end_byte
+                    && highlight.range.end_byte >= edit.start_byte
+            }) {
+                *row = None;
+                continue;
+            }
+
+            for highlight in highlights {
+                if highlight.range.start_byte > edit.old_end_byte {
+                    let range = &mut highlight.range;
+                    range.start_byte = range.start_byte - edit.old_end_byte + edit.new_end_byte;
+                    range.end_byte = range.end_byte - edit.old_end_byte + edit.new_end_byte;
+                    range.start_point = shift_point(range.start_point, edit);
+                    range.end_point = shift_point(range.end_point, edit);
+                }
+            }
+        }
+    }
+
+    /// Query the highlights of every row in `rows` that isn't cached.
+    pub fn fill(
+        &mut self,
+        buffer: &Buffer,
+        query: &Query,
+        predicates: &Predicates,
+        rows: ops::Range<usize>,
+    ) {
+        let mut row = rows.start;
+        while row < rows.end {
+            if self.rows[row].is_some() {
+                row += 1;
+                continue;
+            }
+            let start = row;
+            while row < rows.end && self.rows[row].is_none() {
+                row += 1;
+            }
+            self.query(buffer, query, predicates, start..row);
+        }
+    }
+
+    /// Query the highlights of `rows`, restricting the query cursor to their text.
+    fn query(
+        &mut self,
+        buffer: &Buffer,
+        query: &Query,
+        predicates: &Predicates,
+        rows: ops::Range<usize>,
+    ) {
+        let bytes = buffer.rope.line_to_byte(rows.start)..buffer.rope.line_to_byte(rows.end);
+        let mut query_cursor = QueryCursor::new();
+        query_cursor.set_byte_range(bytes.clone());
+        let text = buffer.rope.slice(..);
+        let matches = query_cursor
+            .matches(query, buffer.tree.root_node(), RopeProvider { slice: text })
+            .filter(|mat| predicates.is_match(query, mat, text));
+
+        self.rows[rows.clone()].fill(Some(Vec::new()));
+        for highlight in Highlight::from_matches(query, matches) {
+            let range = &highlight.range;
+            if range.start_byte >= bytes.end || range.end_byte <= bytes.start {
+                continue;
+            }
+
+            // Highlights that end at the start of a row don't overlap it.
+            let end_row =
+                if range.end_point.column == 0 && range.end_point.row > range.start_point.row {
+                    range.end_point.row - 1
+                } else {
+                    range.end_point.row
+                };
+            for row in range.start_point.row.max(rows.start)..(end_row + 1).min(rows.end) {
+                if let Some(highlights) = &mut self.rows[row] {
+                    highlights.push(highlight.clone());
+                }
+            }
+        }
+    }
+
+    /// Cached highlights that overlap `rows`.
+    ///
+    /// Highlights that span more than one row are repeated and are deduplicated by [`HighlightIndex::new`].
+    pub fn highlights(&self, rows: ops::Range<usize>) -> Vec<Highlight> {
+        self.rows[rows]
+            .iter()
+            .flatten()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+}
+
+/// Move a `point` after an edit to its position in the edited text.
+fn shift_point(point: Point, edit: &InputEdit) -> Point {
+    if point.row == edit.old_end_position.row {
+        Point::new(
+            edit.new_end_position.row,
+            point.column - edit.old_end_position.column + edit.new_end_position.column,
+        )
+    } else {
+        Point::new(
+            point.row - edit.old_end_position.row + edit.new_end_position.row,
+            point.column,
+        )
+    }
+}
+
+/// Highlights sorted by byte offset that don't overlap, to find the highlights of a range of text.
+#[derive(Debug, Default)]
+pub struct HighlightIndex {
+    highlights: Vec<Highlight>,
+}
+
+impl HighlightIndex {
+    /// Index the highlights of each layer, resolving the highlights that overlap.
+    ///
+    /// Like `tree-sitter-highlight`, nested captures take precedence over the captures around them,
+    /// and when a node is captured more than once the earliest pattern wins.
+    /// Later layers, like injected languages, take precedence over earlier ones for the same text.
+    pub fn new(layers: impl IntoIterator<Item = Vec<Highlight>>) -> Self {
+        Self {
+            highlights: resolve(layers),
+        }
+    }
+
+    /// Highlights that overlap the byte range `bytes`, sorted by byte offset.
+    pub fn overlapping(&self, bytes: ops::Range<usize>) -> &[Highlight] {
+        let start = self
+            .highlights
+            .partition_point(|highlight| highlight.range.end_byte <= bytes.start);
+        let len = self.highlights[start..]
+            .partition_point(|highlight| highlight.range.start_byte < bytes.end);
+        &self.highlights[start..start + len]
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Highlight> {
+        self.highlights.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.highlights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.highlights.is_empty()
+    }
+}
+
+fn resolve(layers: impl IntoIterator<Item = Vec<Highlight>>) -> Vec<Highlight> {
+    let mut captures: Vec<_> = layers
+        .into_iter()
+        .enumerate()
+        .flat_map(|(layer_idx, highlights)| {
+            highlights
+                .into_iter()
+                .filter(|highlight| highlight.range.start_byte < highlight.range.end_byte)
+                .map(move |highlight| (layer_idx, highlight))
+        })
+        .collect();
+    captures.sort_by_key(|(layer_idx, highlight)| {
+        (
+            highlight.range.start_byte,
+            Reverse(highlight.range.end_byte),
+            Reverse(*layer_idx),
+            highlight.pattern_index,
+        )
+    });
+    captures.dedup_by(|(_, highlight), (_, first)| {
+        highlight.range.start_byte == first.range.start_byte
+            && highlight.range.end_byte == first.range.end_byte
+    });
+
+    let mut resolved = Vec::new();
+    let mut stack: Vec<Highlight> = Vec::new();
+    let mut start = (0, Point::default());
+    for (_, mut highlight) in captures {
+        // Finish the captures that end before this one starts.
+        while let Some(outer) = stack.last() {
+            if outer.range.end_byte > highlight.range.start_byte {
+                break;
+            }
+            let end = (outer.range.end_byte, outer.range.end_point);
+            push_segment(&mut resolved, outer, start, end);
+            start = end;
+            stack.pop();
+        }
+
+        if let Some(outer) = stack.last() {
+            let end = (highlight.range.start_byte, highlight.range.start_point);
+            push_segment(&mut resolved, outer, start, end);
+
+            // Captures from different layers can partially overlap, so keep them inside the outer capture.
+            if highlight.range.end_byte > outer.range.end_byte {
+                highlight.range.end_byte = outer.range.end_byte;
+                highlight.range.end_point = outer.range.end_point;
+            }
+        }
+        start = (highlight.range.start_byte, highlight.range.start_point);
+        stack.push(highlight);
+    }
+
+    while let Some(outer) = stack.pop() {
+        let end = (outer.range.end_byte, outer.range.end_point);
+        push_segment(&mut resolved, &outer, start, end);
+        start = end;
+    }
+    resolved
+}
+
+/// Push the part of `highlight` from `start` to `end`, if it isn't empty.
+fn push_segment(
+    resolved: &mut Vec<Highlight>,
+    highlight: &Highlight,
+    start: (usize, Point),
+    end: (usize, Point),
+) {
+    if start.0 < end.0 {
+        resolved.push(Highlight {
+            name: highlight.name.clone(),
+            pattern_index: highlight.pattern_index,
+            range: Range {
+                start_byte: start.0,
+                end_byte: end.0,
+                start_point: start.1,
+                end_point: end.1,
+            },
+        });
+    }
+}
@@ -1,47 +1,78 @@
-use crate::{buffer::RopeProvider, Buffer, Language};
-use dioxus::prelude::{use_context, use_effect, Scope};
+use crate::{buffer::RopeProvider, use_language, Buffer, Error, Language};
+use dioxus::prelude::{use_context, Scope};
 use dioxus_signals::{use_signal, Signal};
+use std::{cell::RefCell, rc::Rc};
 use tree_sitter_c2rust::{Query, QueryCursor, QueryMatches};
 
-pub fn use_language<T>(cx: Scope<T>) -> Signal<Language> {
-    *use_context(cx).unwrap()
-}
-
 pub fn use_query<T>(
     cx: Scope<T>,
     query: &str,
     buffer: Signal<Buffer>,
-    mut onmatches: impl FnMut(QueryMatches<RopeProvider>) + 'static,
+    mut onmatches: impl FnMut(&Query, QueryMatches<RopeProvider>) + 'static,
 ) {
     let query_signal = use_query_signal(cx, query);
+    let error_signal = use_context::<Signal<Option<Error>>>(cx).copied();
     dioxus_signals::use_effect(cx, move || {
         let buffer_ref = buffer();
-        let query = query_signal();
+        let query_ref = query_signal();
+        let query = match &*query_ref {
+            Ok(query) => query,
+            Err(error) => {
+                if let Some(error_signal) = error_signal {
+                    error_signal.set(Some(error.clone()));
+                }
+                return;
+            }
+        };
 
         let mut query_cursor = QueryCursor::new();
         let matches = query_cursor.matches(
-            &query,
+            query,
             buffer_ref.tree.root_node(),
             RopeProvider {
                 slice: buffer_ref.rope.slice(..),
             },
         );
-        onmatches(matches)
+        onmatches(query, matches)
     });
 }
 
-pub fn use_query_signal<T>(cx: Scope<T>, query: &str) -> Signal<Query> {
+/// Compile `query` for the current language, updating the signal when either changes.
+///
+/// Invalid queries are logged and stored as an error instead of panicking.
+pub fn use_query_signal<T>(cx: Scope<T>, query: &str) -> Signal<Result<Query, Error>> {
     let language = use_language(cx);
-    let signal = use_signal(cx, || Query::new(language().tree_sitter, query).unwrap());
+    let source = use_signal(cx, || query.to_owned());
+    if *source.read() != query {
+        source.set(query.to_owned());
+    }
 
-    use_effect(cx, &query.to_owned(), |q| async move {
-        signal.set(Query::new(language().tree_sitter, &q).unwrap())
-    });
+    let signal = use_signal(cx, || compile(language, query));
 
-    let query = query.to_owned();
+    // The query is compiled for the first render above, so it's only compiled again when the language or query changes.
+    let compiled = cx
+        .use_hook(|| {
+            let key = (language.map(|language| *language()), query.to_owned());
+            Rc::new(RefCell::new(key))
+        })
+        .clone();
     dioxus_signals::use_effect(cx, move || {
-        signal.set(Query::new(language().tree_sitter, &query).unwrap())
+        let key = (language.map(|language| *language()), source().clone());
+        let mut compiled = compiled.borrow_mut();
+        if *compiled != key {
+            signal.set(compile(language, &key.1));
+            *compiled = key;
+        }
     });
 
     signal
 }
+
+fn compile(language: Option<Signal<Language>>, query: &str) -> Result<Query, Error> {
+    let language = language.ok_or(Error::MissingLanguage)?;
+    let result = Query::new(language().tree_sitter, query).map_err(Error::from);
+    if let Err(error) = &result {
+        log::error!("Invalid query: {}", error);
+    }
+    result
+}
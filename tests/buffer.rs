@@ -0,0 +1,161 @@
+use engrave::{Buffer, Range};
+use tree_sitter_c2rust::{Parser, Point, Tree};
+
+/// Kind, byte range and position of every node of `tree`, in order.
+fn nodes(tree: &Tree) -> Vec<(&'static str, usize, usize, Point, Point)> {
+    let mut nodes = Vec::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        nodes.push((
+            node.kind(),
+            node.start_byte(),
+            node.end_byte(),
+            node.start_position(),
+            node.end_position(),
+        ));
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        loop {
+            if !cursor.goto_parent() {
+                return nodes;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+}
+
+fn parse(text: &str) -> Tree {
+    let mut parser = Parser::new();
+    parser.set_language(tree_sitter_rust::language()).unwrap();
+    parser.parse(text, None).unwrap()
+}
+
+#[test]
+fn it_deletes_ranges_in_either_direction() {
+    let text = "fn a() {\n    let é = 1;\n}\nfn b() {}\n";
+    let mut buffer = Buffer::new(tree_sitter_rust::language(), text);
+
+    // A selection made backwards deletes the same text as one made forwards.
+    buffer.delete(Range::new(Point::new(1, 11), Point::new(0, 8)));
+    assert_eq!(buffer.rope.to_string(), "fn a() { 1;\n}\nfn b() {}\n");
+    assert_eq!(nodes(&buffer.tree), nodes(&parse(&buffer.rope.to_string())));
+
+    buffer.delete(Range::new(Point::new(0, 0), Point::new(2, 0)));
+    assert_eq!(buffer.rope.to_string(), "fn b() {}\n");
+    assert_eq!(nodes(&buffer.tree), nodes(&parse(&buffer.rope.to_string())));
+
+    // Empty ranges don't change the text.
+    buffer.delete(Range::point(Point::new(0, 3)));
+    assert_eq!(buffer.rope.to_string(), "fn b() {}\n");
+}
+
+#[test]
+fn it_replaces_ranges() {
+    let mut buffer = Buffer::new(tree_sitter_rust::language(), "fn a() {}\nfn b() {}\n");
+
+    buffer.replace(
+        Range::new(Point::new(1, 4), Point::new(0, 3)),
+        "x() {}\nfn y",
+    );
+    assert_eq!(buffer.rope.to_string(), "fn x() {}\nfn y() {}\n");
+    assert_eq!(nodes(&buffer.tree), nodes(&parse(&buffer.rope.to_string())));
+
+    buffer.replace(Range::point(Point::new(2, 0)), "struct S;");
+    buffer.replace(Range::new(Point::new(0, 0), Point::new(1, 0)), "");
+    assert_eq!(buffer.rope.to_string(), "fn y() {}\nstruct S;");
+    assert_eq!(nodes(&buffer.tree), nodes(&parse(&buffer.rope.to_string())));
+}
+
+#[test]
+fn it_converts_between_points_and_characters() {
+    let buffer = Buffer::new(tree_sitter_rust::language(), "é\nab\n");
+
+    assert_eq!(buffer.point_to_char(Point::new(1, 1)), 3);
+    assert_eq!(buffer.char_to_point(3), Point::new(1, 1));
+
+    // Points past the end of a line or the text are clamped to it.
+    assert_eq!(buffer.point_to_char(Point::new(0, 5)), 2);
+    assert_eq!(buffer.point_to_char(Point::new(9, 0)), 5);
+    assert_eq!(buffer.char_to_point(9), Point::new(2, 0));
+}
+
+#[test]
+fn it_sorts_ranges() {
+    let range = Range::new(Point::new(1, 0), Point::new(0, 2)).sorted();
+    assert_eq!(range.start, Point::new(0, 2));
+    assert_eq!(range.end, Point::new(1, 0));
+    assert_eq!(range.sorted().start, range.start);
+
+    assert!(!range.is_empty());
+    assert!(Range::point(Point::new(1, 0)).is_empty());
+}
+
+/// Nodes that start or end on each row of the tree of `text`.
+fn rows(text: &str) -> Vec<Vec<(&'static str, Point, Point)>> {
+    let mut rows = vec![Vec::new(); text.lines().count() + 1];
+    for (kind, _, _, start, end) in nodes(&parse(text)) {
+        rows[start.row].push((kind, start, end));
+        rows[end.row].push((kind, start, end));
+    }
+    rows
+}
+
+#[test]
+fn it_reparses_edits_across_lines_like_a_fresh_parse() {
+    let mut buffer = Buffer::new(
+        tree_sitter_rust::language(),
+        "fn a() {\n    let ü = 1;\n}\n\nfn b() {}\n",
+    );
+
+    let edits = [
+        (Range::point(Point::new(1, 14)), "\n    let x = \"ü\nü\";"),
+        (Range::new(Point::new(0, 8), Point::new(2, 5)), ""),
+        (Range::point(Point::new(3, 0)), "// é\n/* a\nb */"),
+        (
+            Range::new(Point::new(1, 0), Point::new(5, 0)),
+            "struct S;\n",
+        ),
+    ];
+    for (range, text) in edits {
+        buffer.replace(range, text);
+        let fresh = parse(&buffer.rope.to_string());
+        assert_eq!(nodes(&buffer.tree), nodes(&fresh), "{}", buffer.rope);
+    }
+}
+
+#[test]
+fn it_edits_the_tree_with_byte_positions() {
+    let mut buffer = Buffer::new(tree_sitter_rust::language(), "let é = 1;\n");
+    buffer.insert(0, 5, "ü\nüx");
+
+    let edit = buffer.edits()[0];
+    assert_eq!(edit.start_position, Point::new(0, 6));
+    assert_eq!(edit.old_end_position, Point::new(0, 6));
+    assert_eq!(edit.new_end_position, Point::new(1, 3));
+    assert_eq!(edit.new_end_byte, edit.start_byte + "ü\nüx".len());
+}
+
+#[test]
+fn it_reports_the_rows_that_changed() {
+    let text = "let a = 1;\nlet b = 2;\nlet c = \"x\";\nlet d = 4;\n";
+    let mut buffer = Buffer::new(tree_sitter_rust::language(), text);
+
+    buffer.insert(1, 5, "x");
+    assert_eq!(buffer.changed_rows(), Some(1..2));
+
+    // Opening a string changes the syntax of the rows after it, which a fresh parse also sees.
+    let before = rows(&buffer.rope.to_string());
+    buffer.insert(0, 8, "\"");
+    let after = rows(&buffer.rope.to_string());
+    let changed = buffer.changed_rows().unwrap();
+    for row in 0..after.len() {
+        if before[row] != after[row] {
+            assert!(changed.contains(&row), "{row} isn't in {changed:?}");
+        }
+    }
+    assert!(changed.end > 1);
+}
@@ -0,0 +1,67 @@
+use engrave::{column, language, Buffer, Motion};
+use ropey::Rope;
+use tree_sitter_c2rust::Point;
+
+#[test]
+fn it_converts_char_columns_to_display_columns() {
+    let rope = Rope::from_str("a\tb中文e\u{301}x\n");
+    let line = rope.line(0);
+
+    let display: Vec<_> = (0..8)
+        .map(|column| column::char_to_display(line, column, 4))
+        .collect();
+    // Tabs expand to the next tab stop, wide characters take two cells,
+    // and a combining accent sits at the end of its cluster.
+    assert_eq!(display, [0, 1, 4, 5, 7, 9, 10, 10]);
+}
+
+#[test]
+fn it_converts_display_columns_to_char_columns() {
+    let rope = Rope::from_str("a\tb中文\r\n");
+    let line = rope.line(0);
+
+    let columns: Vec<_> = (0..12)
+        .map(|display| column::display_to_char(line, display, 4))
+        .collect();
+    // Columns inside a tab or a wide character go to its start, and columns past the end go to the line ending.
+    assert_eq!(columns, [0, 1, 1, 1, 2, 3, 3, 4, 4, 5, 5, 5]);
+}
+
+#[test]
+fn it_moves_vertically_by_display_column() {
+    let buffer = Buffer::new(language::rust().tree_sitter, "\tx = 1;\n中文 = 2;\nab\n");
+
+    // The tab ends at display column 4, which is after the second wide character.
+    let (point, goal) = Motion::Down.apply(&buffer, Point::new(0, 1), None, 1, 4);
+    assert_eq!((point, goal), (Point::new(1, 2), Some(4)));
+
+    // Short lines clamp the cursor without forgetting the goal column.
+    let (point, goal) = Motion::Down.apply(&buffer, point, goal, 1, 4);
+    assert_eq!((point, goal), (Point::new(2, 2), Some(4)));
+    let (point, _) = Motion::Up.apply(&buffer, point, goal, 1, 4);
+    assert_eq!(point, Point::new(1, 2));
+}
+
+#[test]
+fn it_finds_grapheme_boundaries_across_chunks() {
+    let text = "e\u{301}👩\u{200d}👩\u{200d}👧\r\n".repeat(500);
+    let rope = Rope::from_str(&text);
+    assert!(rope.chunks().count() > 1);
+
+    let mut boundaries = vec![0];
+    while let Some(&char_idx) = boundaries.last().filter(|idx| **idx < rope.len_chars()) {
+        boundaries.push(column::next_grapheme(rope.slice(..), char_idx));
+    }
+    let mut expected = vec![0];
+    for grapheme in unicode_segmentation::UnicodeSegmentation::graphemes(&*text, true) {
+        expected.push(expected.last().unwrap() + grapheme.chars().count());
+    }
+    assert_eq!(boundaries, expected);
+
+    let mut prev_boundaries = vec![rope.len_chars()];
+    while let Some(&char_idx) = prev_boundaries.last().filter(|idx| **idx > 0) {
+        prev_boundaries.push(column::prev_grapheme(rope.slice(..), char_idx));
+    }
+    prev_boundaries.reverse();
+    assert_eq!(prev_boundaries, expected);
+}
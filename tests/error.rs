@@ -0,0 +1,34 @@
+use engrave::{Buffer, Error};
+use tree_sitter_c2rust::Query;
+
+#[test]
+fn it_creates_buffers_with_compatible_languages() {
+    let buffer = Buffer::try_new(tree_sitter_rust::language(), "fn main() {}\n").unwrap();
+    assert_eq!(buffer.rope.to_string(), "fn main() {}\n");
+    assert_eq!(buffer.tree.root_node().kind(), "source_file");
+}
+
+#[test]
+fn it_converts_invalid_queries_to_errors() {
+    let error: Error = Query::new(tree_sitter_rust::language(), "(not_a_node) @name")
+        .unwrap_err()
+        .into();
+    let Error::Query(message) = &error else {
+        panic!("expected a query error, got {error:?}");
+    };
+    assert!(message.contains("not_a_node"), "{message}");
+    assert_eq!(error.to_string(), *message);
+}
+
+#[test]
+fn it_describes_errors() {
+    assert_eq!(Error::Parse.to_string(), "failed to parse text");
+    assert_eq!(
+        Error::Language("version 99".into()).to_string(),
+        "incompatible language: version 99"
+    );
+    assert_eq!(
+        Error::MissingLanguage.to_string(),
+        "no language was provided by an editor"
+    );
+}
@@ -0,0 +1,175 @@
+use engrave::{
+    language, Buffer, Highlight, HighlightCache, HighlightIndex, Predicates, Range, RopeProvider,
+};
+use std::ops;
+use tree_sitter_c2rust::{Point, Query, QueryCursor, Range as TsRange};
+
+/// Name and byte range of each resolved highlight in `index` that overlaps `rows` of `buffer`.
+fn resolved(
+    buffer: &Buffer,
+    index: &HighlightIndex,
+    rows: ops::Range<usize>,
+) -> Vec<(String, usize, usize)> {
+    let bytes = buffer.rope.line_to_byte(rows.start)..buffer.rope.line_to_byte(rows.end);
+    index
+        .overlapping(bytes)
+        .iter()
+        .map(|highlight| {
+            let range = &highlight.range;
+            (highlight.name.clone(), range.start_byte, range.end_byte)
+        })
+        .collect()
+}
+
+/// Highlights of `rows` from a query over the whole buffer, without a cache.
+fn queried(buffer: &Buffer, query: &Query, rows: ops::Range<usize>) -> Vec<(String, usize, usize)> {
+    let predicates = Predicates::default();
+    let text = buffer.rope.slice(..);
+    let mut query_cursor = QueryCursor::new();
+    let matches = query_cursor
+        .matches(query, buffer.tree.root_node(), RopeProvider { slice: text })
+        .filter(|mat| predicates.is_match(query, mat, text));
+    let index = HighlightIndex::new([Highlight::from_matches(query, matches)]);
+    resolved(buffer, &index, rows)
+}
+
+/// Update `cache` to `buffer` and highlight `rows`.
+fn cached(
+    cache: &mut HighlightCache,
+    buffer: &Buffer,
+    query: &Query,
+    rows: ops::Range<usize>,
+) -> Vec<(String, usize, usize)> {
+    cache.update(buffer, language::rust().highlight_query);
+    cache.fill(buffer, query, &Predicates::default(), rows.clone());
+    let index = HighlightIndex::new([cache.highlights(rows.clone())]);
+    resolved(buffer, &index, rows)
+}
+
+fn rust_query() -> Query {
+    let rust = language::rust();
+    Query::new(rust.tree_sitter, rust.highlight_query).unwrap()
+}
+
+#[test]
+fn it_only_queries_the_filled_rows() {
+    let query = rust_query();
+    let buffer = Buffer::new(
+        language::rust().tree_sitter,
+        &"fn main() { let x = \"a\"; }\n".repeat(100),
+    );
+    let mut cache = HighlightCache::default();
+
+    assert_eq!(
+        cached(&mut cache, &buffer, &query, 40..60),
+        queried(&buffer, &query, 40..60)
+    );
+    assert!(cache.highlights(0..40).is_empty());
+    assert!(cache.highlights(60..100).is_empty());
+}
+
+#[test]
+fn it_highlights_edits_like_a_full_query() {
+    let query = rust_query();
+    let mut buffer = Buffer::new(
+        language::rust().tree_sitter,
+        "fn a() {\n    let x = 1;\n}\n\nfn b() -> &'static str {\n    \"b\"\n}\n\nstruct C;\n",
+    );
+    let mut cache = HighlightCache::default();
+    cached(&mut cache, &buffer, &query, 0..buffer.rope.len_lines());
+
+    let edits: [(Range, &str); 6] = [
+        // Inside a line.
+        (Range::point(Point::new(1, 8)), "y, "),
+        // New lines, which move the highlights below them.
+        (Range::point(Point::new(3, 0)), "fn c() {}\nfn d() {}\n"),
+        // Across lines.
+        (Range::new(Point::new(1, 4), Point::new(4, 3)), ""),
+        // A block comment, which changes the syntax of the rows after it.
+        (Range::point(Point::new(0, 0)), "/*\n"),
+        (Range::point(Point::new(4, 0)), "*/"),
+        // Everything.
+        (
+            Range::new(Point::new(0, 0), Point::new(10, 0)),
+            "const A: u8 = 0;\n",
+        ),
+    ];
+    for (range, text) in edits {
+        buffer.replace(range, text);
+        let rows = 0..buffer.rope.len_lines();
+        assert_eq!(
+            cached(&mut cache, &buffer, &query, rows.clone()),
+            queried(&buffer, &query, rows),
+            "{}",
+            buffer.rope
+        );
+    }
+}
+
+#[test]
+fn it_forgets_every_row_after_missed_versions() {
+    let query = rust_query();
+    let mut buffer = Buffer::new(language::rust().tree_sitter, "fn a() {}\nfn b() {}\n");
+    let mut cache = HighlightCache::default();
+    cached(&mut cache, &buffer, &query, 0..3);
+
+    // The cache doesn't see the first edit, so it can't move its highlights.
+    buffer.insert(0, 0, "struct S;\n");
+    buffer.insert(2, 3, "c");
+    assert_eq!(
+        cached(&mut cache, &buffer, &query, 0..4),
+        queried(&buffer, &query, 0..4)
+    );
+}
+
+/// Highlight of the bytes `bytes` on the first row.
+fn highlight(name: &str, pattern_index: usize, bytes: ops::Range<usize>) -> Highlight {
+    Highlight {
+        name: name.to_owned(),
+        pattern_index,
+        range: TsRange {
+            start_byte: bytes.start,
+            end_byte: bytes.end,
+            start_point: Point::new(0, bytes.start),
+            end_point: Point::new(0, bytes.end),
+        },
+    }
+}
+
+#[test]
+fn it_resolves_overlapping_captures_by_precedence() {
+    let host = vec![
+        highlight("variable", 2, 0..10),
+        highlight("function", 0, 0..10),
+        highlight("string", 1, 10..20),
+        highlight("escape", 3, 13..15),
+        highlight("comment", 0, 20..30),
+    ];
+    let injected = vec![
+        highlight("type", 5, 20..30),
+        highlight("keyword", 0, 24..26),
+    ];
+    let index = HighlightIndex::new([host, injected]);
+
+    let resolved: Vec<_> = index
+        .iter()
+        .map(|highlight| {
+            let range = &highlight.range;
+            (highlight.name.as_str(), range.start_byte, range.end_byte)
+        })
+        .collect();
+    // The earliest pattern wins for the same node, nested captures split the captures around them,
+    // and injected layers win over the host for the same text, even with a later pattern.
+    assert_eq!(
+        resolved,
+        [
+            ("function", 0, 10),
+            ("string", 10, 13),
+            ("escape", 13, 15),
+            ("string", 15, 20),
+            ("type", 20, 24),
+            ("keyword", 24, 26),
+            ("type", 26, 30),
+        ]
+    );
+}
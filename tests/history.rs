@@ -0,0 +1,100 @@
+use engrave::{Buffer, EditKind, Range};
+use tree_sitter_c2rust::Point;
+
+/// Type each of `texts` before the last line ending as its own transaction, like key presses.
+fn type_text(buffer: &mut Buffer, texts: &[&str]) {
+    for text in texts {
+        let end = buffer.char_to_point(buffer.rope.len_chars() - 1);
+        buffer.begin_transaction(EditKind::Insert, &[Range::point(end)]);
+        buffer.insert(end.row, end.column, text);
+        let after = buffer.char_to_point(buffer.rope.len_chars() - 1);
+        buffer.commit_transaction(&[Range::point(after)]);
+    }
+}
+
+#[test]
+fn it_undoes_and_redoes_transactions() {
+    let mut buffer = Buffer::new(tree_sitter_rust::language(), "fn a() {}\n");
+    let before = Range::new(Point::new(0, 3), Point::new(0, 4));
+    let after = Range::point(Point::new(0, 6));
+    buffer.begin_transaction(EditKind::Other, &[before]);
+    buffer.replace(before, "bcd");
+    buffer.commit_transaction(&[after]);
+
+    assert_eq!(buffer.undo(), Some(vec![before]));
+    assert_eq!(buffer.rope.to_string(), "fn a() {}\n");
+    assert_eq!(buffer.undo(), None);
+
+    assert_eq!(buffer.redo(), Some(vec![after]));
+    assert_eq!(buffer.rope.to_string(), "fn bcd() {}\n");
+    assert_eq!(buffer.redo(), None);
+}
+
+#[test]
+fn it_merges_typed_characters_until_a_new_line() {
+    let mut buffer = Buffer::new(tree_sitter_rust::language(), "\n");
+    type_text(&mut buffer, &["a", "b", "\n", "c"]);
+    assert_eq!(buffer.rope.to_string(), "ab\nc\n");
+
+    // The new line starts an undo step that the characters typed after it join.
+    assert_eq!(buffer.undo(), Some(vec![Range::point(Point::new(0, 2))]));
+    assert_eq!(buffer.rope.to_string(), "ab\n");
+    assert_eq!(buffer.undo(), Some(vec![Range::point(Point::new(0, 0))]));
+    assert_eq!(buffer.rope.to_string(), "\n");
+}
+
+#[test]
+fn it_doesnt_merge_sealed_or_different_edits() {
+    let mut buffer = Buffer::new(tree_sitter_rust::language(), "\n");
+    type_text(&mut buffer, &["a"]);
+    buffer.seal_history();
+    type_text(&mut buffer, &["b"]);
+
+    let cursor = Range::point(Point::new(0, 2));
+    buffer.begin_transaction(EditKind::Delete, &[cursor]);
+    buffer.delete(Range::new(Point::new(0, 1), Point::new(0, 2)));
+    buffer.commit_transaction(&[Range::point(Point::new(0, 1))]);
+
+    for text in ["ab\n", "a\n", "\n"] {
+        buffer.undo();
+        assert_eq!(buffer.rope.to_string(), text);
+    }
+}
+
+#[test]
+fn it_forgets_undone_edits_after_a_new_edit() {
+    let mut buffer = Buffer::new(tree_sitter_rust::language(), "\n");
+    type_text(&mut buffer, &["a"]);
+    buffer.undo();
+    type_text(&mut buffer, &["b"]);
+
+    assert_eq!(buffer.redo(), None);
+    assert_eq!(buffer.rope.to_string(), "b\n");
+}
+
+#[test]
+fn it_ignores_goal_columns_when_comparing_ranges() {
+    let mut range = Range::new(Point::new(1, 2), Point::new(0, 4));
+    range.goal_column = Some(8);
+
+    assert_eq!(range, Range::new(Point::new(1, 2), Point::new(0, 4)));
+    assert_eq!(range.sorted().goal_column, Some(8));
+}
+
+#[test]
+fn it_merges_typing_after_the_goal_column_changes() {
+    let mut buffer = Buffer::new(tree_sitter_rust::language(), "\n");
+    let mut cursor = Range::point(Point::new(0, 0));
+    for (column, text) in ["a", "b"].into_iter().enumerate() {
+        buffer.begin_transaction(EditKind::Insert, &[cursor]);
+        buffer.insert(0, column, text);
+        cursor = Range::point(Point::new(0, column + 1));
+        buffer.commit_transaction(&[cursor]);
+
+        // Only the goal column changes between the transactions, like after moving up from the first line.
+        cursor.goal_column = Some(4);
+    }
+
+    buffer.undo();
+    assert_eq!(buffer.rope.to_string(), "\n");
+}
@@ -0,0 +1,161 @@
+use engrave::{language, Buffer, LanguageRegistry, Predicates, Range};
+use tree_sitter_c2rust::Point;
+
+/// Language and injected start and end bytes of every layer, in the order they appear in the text.
+fn layers(buffer: &Buffer) -> Vec<(&'static str, Vec<(usize, usize)>)> {
+    let mut layers: Vec<_> = buffer
+        .injection_layers()
+        .iter()
+        .map(|layer| {
+            let ranges = layer.tree.included_ranges();
+            let bytes = ranges
+                .iter()
+                .map(|range| (range.start_byte, range.end_byte));
+            (layer.language.name, bytes.collect::<Vec<_>>())
+        })
+        .collect();
+    layers.sort_by_key(|(_, ranges)| ranges[0]);
+    layers
+}
+
+/// Text of the injected highlights captured as `name`.
+fn highlighted(buffer: &Buffer, name: &str) -> Vec<String> {
+    buffer
+        .injection_highlights(0..buffer.rope.len_lines(), &Predicates::default())
+        .into_iter()
+        .filter(|highlight| highlight.name == name)
+        .map(|highlight| {
+            let bytes = highlight.range.start_byte..highlight.range.end_byte;
+            buffer.rope.byte_slice(bytes).to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn it_injects_rust_into_rsx_macros() {
+    let mut buffer = Buffer::new(
+        language::rust().tree_sitter,
+        "fn app() -> Element {\n    rsx! { div { class: \"a\" } }\n}\n",
+    );
+    buffer
+        .set_injections(
+            language::rust().injection_query,
+            LanguageRegistry::default(),
+        )
+        .unwrap();
+
+    assert_eq!(layers(&buffer), [("rust", vec![(32, 52)])]);
+    assert_eq!(highlighted(&buffer, "type"), ["div"]);
+    assert_eq!(highlighted(&buffer, "property"), ["class"]);
+}
+
+#[test]
+fn it_injects_without_the_markdown_grammar() {
+    let rust = language::rust();
+    let mut registry = LanguageRegistry::empty();
+    registry.register(rust);
+    let mut buffer = Buffer::new(
+        rust.tree_sitter,
+        "/// Doc comment\nfn app() -> Element {\n    render! { div {} }\n}\n",
+    );
+    buffer
+        .set_injections(rust.injection_query, registry)
+        .unwrap();
+
+    // Doc comments stay comments without a markdown language, but macros are still injected.
+    assert_eq!(layers(&buffer), [("rust", vec![(51, 59)])]);
+
+    buffer.insert(2, 19, " class: \"a\"");
+    assert_eq!(layers(&buffer), [("rust", vec![(51, 70)])]);
+    assert_eq!(highlighted(&buffer, "type"), ["div"]);
+    assert_eq!(highlighted(&buffer, "property"), ["class"]);
+}
+
+#[cfg(all(feature = "markdown", not(target_arch = "wasm32")))]
+#[test]
+fn it_injects_fenced_code_blocks() {
+    let markdown = language::markdown();
+    let mut buffer = Buffer::new(
+        markdown.tree_sitter,
+        "# Title\n\n```rust\nfn main() {}\n```\n\n```unknown\nx\n```\n",
+    );
+    buffer
+        .set_injections(markdown.injection_query, LanguageRegistry::default())
+        .unwrap();
+
+    // Fences in unregistered languages aren't injected.
+    assert_eq!(layers(&buffer), [("rust", vec![(17, 30)])]);
+    assert_eq!(highlighted(&buffer, "keyword"), ["fn"]);
+    assert_eq!(highlighted(&buffer, "function"), ["main"]);
+}
+
+#[cfg(all(feature = "markdown", not(target_arch = "wasm32")))]
+#[test]
+fn it_injects_markdown_into_doc_comments() {
+    let rust = language::rust();
+    let mut buffer = Buffer::new(
+        rust.tree_sitter,
+        "/// # Title\n// Not a doc comment\n/// `code`\nfn main() {}\n",
+    );
+    buffer
+        .set_injections(rust.injection_query, LanguageRegistry::default())
+        .unwrap();
+
+    // Doc comments are parsed together as one document.
+    assert_eq!(layers(&buffer), [("markdown", vec![(3, 12), (36, 44)])]);
+    let layer = &buffer.injection_layers()[0];
+    assert!(!layer.tree.root_node().has_error());
+    assert_eq!(
+        layer.tree.root_node().child(0).unwrap().start_position(),
+        Point::new(0, 3)
+    );
+
+    // New doc comments are added to the document.
+    buffer.insert(3, 0, "/// More\n");
+    assert_eq!(
+        layers(&buffer),
+        [("markdown", vec![(3, 12), (36, 44), (47, 53)])]
+    );
+}
+
+#[cfg(all(feature = "markdown", not(target_arch = "wasm32")))]
+#[test]
+fn it_updates_injections_like_a_new_buffer() {
+    let registry = LanguageRegistry::default();
+    let markdown = language::markdown();
+    let mut buffer = Buffer::new(
+        markdown.tree_sitter,
+        "```rust\nfn a() {}\n```\n\ntext\n\n```python\nb = 1\n```\n",
+    );
+    buffer
+        .set_injections(markdown.injection_query, registry.clone())
+        .unwrap();
+
+    let assert_updated = |buffer: &Buffer| {
+        let mut expected = Buffer::new(markdown.tree_sitter, &buffer.rope.to_string());
+        expected
+            .set_injections(markdown.injection_query, registry.clone())
+            .unwrap();
+        assert_eq!(layers(buffer), layers(&expected), "{}", buffer.rope);
+    };
+
+    let edits = [
+        // Inside a code block.
+        (1, 3, " b"),
+        // Between the code blocks.
+        (4, 0, "more "),
+        // A new code block.
+        (5, 0, "```json\n[1]\n```\n"),
+        // The language of a code block.
+        (0, 3, "python\n# "),
+    ];
+    for (row, col, text) in edits {
+        buffer.insert(row, col, text);
+        assert_updated(&buffer);
+    }
+
+    // Removing the fences of a code block removes its layer.
+    buffer.delete(Range::new(Point::new(6, 0), Point::new(9, 0)));
+    assert_updated(&buffer);
+    assert!(layers(&buffer).iter().all(|(name, _)| *name != "json"));
+}
@@ -0,0 +1,49 @@
+use dioxus::html::input_data::keyboard_types::{Key, Modifiers};
+use engrave::{KeyChord, Keymap, Lookup};
+
+fn lookup(keymap: &Keymap, key: &str, modifiers: Modifiers) -> Lookup {
+    keymap.lookup(&[KeyChord::new(Key::Character(key.to_owned()), modifiers)])
+}
+
+#[test]
+fn it_ignores_shift_for_symbols() {
+    let mut keymap = Keymap::empty();
+    keymap.bind("Ctrl++", "zoom_in").unwrap();
+    keymap.bind("Ctrl+Shift+Z", "redo").unwrap();
+    keymap.bind("Shift+Space", "page_up").unwrap();
+
+    // Ctrl+Shift+= is pressed as `+` with Shift on a US keyboard.
+    let zoom_in = Lookup::Command("zoom_in".into());
+    assert_eq!(
+        lookup(&keymap, "+", Modifiers::CONTROL | Modifiers::SHIFT),
+        zoom_in
+    );
+    assert_eq!(lookup(&keymap, "+", Modifiers::CONTROL), zoom_in);
+
+    // Shift still counts for letters and whitespace.
+    assert_eq!(
+        lookup(&keymap, "Z", Modifiers::CONTROL | Modifiers::SHIFT),
+        Lookup::Command("redo".into())
+    );
+    assert_eq!(lookup(&keymap, "z", Modifiers::CONTROL), Lookup::None);
+    assert_eq!(lookup(&keymap, " ", Modifiers::empty()), Lookup::None);
+}
+
+#[test]
+fn it_leaves_clipboard_keys_to_the_browser_on_the_web() {
+    let keymap = Keymap::web();
+    for key in ["c", "x", "v"] {
+        assert_eq!(lookup(&keymap, key, Modifiers::CONTROL), Lookup::None);
+        assert_eq!(lookup(&keymap, key, Modifiers::META), Lookup::None);
+    }
+    assert_eq!(
+        lookup(&keymap, "z", Modifiers::CONTROL),
+        Lookup::Command("undo".into())
+    );
+
+    #[cfg(not(target_arch = "wasm32"))]
+    assert_eq!(
+        lookup(&Keymap::default(), "c", Modifiers::CONTROL),
+        Lookup::Command("copy".into())
+    );
+}
@@ -0,0 +1,38 @@
+use engrave::{Buffer, LanguageRegistry};
+use tree_sitter_c2rust::Query;
+
+fn sample(name: &str) -> &'static str {
+    match name {
+        "rust" => "fn main() { let x = 1; }\n",
+        "c" => "int main(void) { return 0; }\n",
+        "html" => "<p class=\"a\">Hello</p>\n",
+        "javascript" => "const f = (a) => a + 1;\n",
+        "json" => "{ \"a\": [1, true, null] }\n",
+        "markdown" => "# Title\n\n```rust\nfn main() {}\n```\n",
+        "python" => "def f(a):\n    return a + 1\n",
+        "toml" => "[package]\nname = \"engrave\"\n",
+        "typescript" => "let a: number = 1;\n",
+        "tsx" => "const a = <div>{b}</div>;\n",
+        _ => panic!("no sample for {name}"),
+    }
+}
+
+#[test]
+fn it_parses_and_queries_every_language() {
+    let registry = LanguageRegistry::default();
+    for language in registry.languages() {
+        let buffer = Buffer::try_new(language.tree_sitter, sample(language.name))
+            .unwrap_or_else(|error| panic!("{}: {error}", language.name));
+        assert!(!buffer.tree().root_node().has_error(), "{}", language.name);
+
+        Query::new(language.tree_sitter, language.highlight_query)
+            .unwrap_or_else(|error| panic!("{} highlight query: {error}", language.name));
+        if !language.injection_query.is_empty() {
+            Query::new(language.tree_sitter, language.injection_query)
+                .unwrap_or_else(|error| panic!("{} injection query: {error}", language.name));
+        }
+    }
+
+    #[cfg(all(feature = "all-languages", not(target_arch = "wasm32")))]
+    assert_eq!(registry.languages().count(), 10);
+}
@@ -0,0 +1,301 @@
+use engrave::{
+    language,
+    layout::{Layout, Monospace},
+    Buffer, Range,
+};
+use ropey::Rope;
+use tree_sitter_c2rust::Point;
+
+/// Lay out every line of `text` with characters 10px wide and rows 20px high.
+fn layout(text: &str, wrap_width: Option<f64>) -> Layout<Monospace> {
+    let rope = Rope::from_str(text);
+    let mut layout = Layout::new(Monospace::with_advance(10.), 20.);
+    layout.set_wrap_width(wrap_width);
+    layout.reset(rope.len_lines());
+    layout.measure(rope.slice(..), 0..rope.len_lines());
+    layout
+}
+
+#[test]
+fn it_positions_characters() {
+    let layout = layout("fn main() {\n    x\n}", None);
+
+    assert_eq!(layout.pos(Point::new(0, 0)), Some([0., 0.]));
+    assert_eq!(layout.pos(Point::new(0, 3)), Some([30., 0.]));
+    assert_eq!(layout.pos(Point::new(1, 4)), Some([40., 20.]));
+    assert_eq!(layout.pos(Point::new(2, 1)), Some([10., 40.]));
+    assert_eq!(layout.pos(Point::new(3, 0)), None);
+    assert_eq!(layout.height(), 60.);
+}
+
+#[test]
+fn it_positions_the_end_of_a_line() {
+    let layout = layout("ab\n\nc", None);
+
+    // Line endings are as wide as a space.
+    assert_eq!(layout.pos(Point::new(0, 2)), Some([20., 0.]));
+    assert_eq!(layout.pos(Point::new(0, 3)), Some([30., 0.]));
+    assert_eq!(layout.pos(Point::new(1, 0)), Some([0., 20.]));
+    assert_eq!(layout.pos(Point::new(2, 5)), Some([10., 40.]));
+}
+
+#[test]
+fn it_expands_tabs_to_tab_stops() {
+    let rope = Rope::from_str("\tx\nab\tc");
+    let mut layout = Layout::new(Monospace::with_advance(10.), 20.);
+    layout.set_tab_width(4);
+    layout.reset(rope.len_lines());
+    layout.measure(rope.slice(..), 0..2);
+
+    assert_eq!(layout.pos(Point::new(0, 1)), Some([40., 0.]));
+    assert_eq!(layout.pos(Point::new(1, 3)), Some([40., 20.]));
+}
+
+#[test]
+fn it_measures_grapheme_clusters() {
+    let layout = layout("e\u{301}x\n中文!", None);
+
+    // The combining accent sits at the end of its cluster without a width of its own.
+    assert_eq!(layout.pos(Point::new(0, 1)), Some([10., 0.]));
+    assert_eq!(layout.pos(Point::new(0, 2)), Some([10., 0.]));
+    assert_eq!(layout.pos(Point::new(0, 3)), Some([20., 0.]));
+
+    // Wide characters take up two cells.
+    assert_eq!(layout.pos(Point::new(1, 1)), Some([20., 20.]));
+    assert_eq!(layout.pos(Point::new(1, 2)), Some([40., 20.]));
+}
+
+#[test]
+fn it_wraps_lines_at_words() {
+    let layout = layout("    foo bar baz\nabcdefghijklmnopqrstuvwxyz", Some(100.));
+
+    // The continuation rows of the first line line up with its indentation.
+    let line = layout.get(0).unwrap();
+    assert_eq!(line.breaks, [8, 12]);
+    assert_eq!(line.indent, 40.);
+    assert_eq!(layout.pos(Point::new(0, 8)), Some([40., 20.]));
+    assert_eq!(layout.pos(Point::new(0, 12)), Some([40., 40.]));
+
+    // Words wider than a row are broken anywhere.
+    assert_eq!(layout.get(1).unwrap().breaks, [10, 20]);
+    assert_eq!(layout.y(1), 60.);
+    assert_eq!(layout.pos(Point::new(1, 21)), Some([10., 100.]));
+    assert_eq!(layout.height(), 120.);
+}
+
+#[test]
+fn it_maps_columns_to_wrapped_rows() {
+    let layout = layout("abc def ghi\nx", Some(40.));
+    let line = layout.get(0).unwrap();
+
+    assert_eq!(line.rows(), 3);
+    assert_eq!(line.row_chars(1), 4..8);
+    assert_eq!(line.row(3), 0);
+    assert_eq!(line.row(4), 1);
+    assert_eq!(line.row(11), 2);
+
+    // Clicking past a wrapped row stays before the space it wraps after.
+    assert_eq!(line.column(0, 100.), 3);
+    assert_eq!(line.column(1, 14.), 5);
+    assert_eq!(line.column(2, 100.), 11);
+    assert_eq!(layout.height(), 80.);
+}
+
+#[test]
+fn it_rewraps_lines_when_the_wrap_width_changes() {
+    let text = Rope::from_str("abc def ghi\nx");
+    let mut layout = layout(&text.to_string(), Some(40.));
+    assert_eq!(layout.height(), 80.);
+
+    layout.set_wrap_width(None);
+    assert!(layout.get(0).is_none());
+    layout.measure(text.slice(..), 0..2);
+    assert_eq!(layout.get(0).unwrap().rows(), 1);
+    assert_eq!(layout.pos(Point::new(0, 8)), Some([80., 0.]));
+    assert_eq!(layout.height(), 40.);
+
+    layout.set_wrap_width(Some(80.));
+    layout.measure(text.slice(..), 0..2);
+    assert_eq!(layout.get(0).unwrap().breaks, [8]);
+    assert_eq!(layout.height(), 60.);
+}
+
+#[test]
+fn it_finds_the_line_at_a_height() {
+    let layout = layout("a\nb c d\ne", Some(40.));
+
+    assert_eq!(layout.line(0.), Some(0));
+    assert_eq!(layout.line(19.), Some(0));
+    assert_eq!(layout.line(21.), Some(1));
+    assert_eq!(layout.line(59.), Some(1));
+    assert_eq!(layout.line(61.), Some(2));
+    assert_eq!(layout.line(81.), None);
+}
+
+#[test]
+fn it_targets_characters() {
+    let layout = layout("abc\ndef ghi", Some(40.));
+
+    assert_eq!(layout.target(0., 10.), Some(Point::new(0, 0)));
+    assert_eq!(layout.target(14., 10.), Some(Point::new(0, 1)));
+    assert_eq!(layout.target(16., 10.), Some(Point::new(0, 2)));
+    assert_eq!(layout.target(24., 30.), Some(Point::new(1, 2)));
+
+    // Wrapped rows are targeted by their height.
+    assert_eq!(layout.target(4., 50.), Some(Point::new(1, 4)));
+    assert_eq!(layout.target(14., 50.), Some(Point::new(1, 5)));
+}
+
+#[test]
+fn it_targets_the_end_of_lines() {
+    assert_eq!(layout("", None).target(0., 100.), Some(Point::new(0, 0)));
+
+    let layout = layout("abc\n\nxy\r\nz", None);
+
+    assert_eq!(layout.target(100., 10.), Some(Point::new(0, 3)));
+    assert_eq!(layout.target(100., 30.), Some(Point::new(1, 0)));
+    assert_eq!(layout.target(-5., 50.), Some(Point::new(2, 0)));
+    assert_eq!(layout.target(100., 50.), Some(Point::new(2, 2)));
+
+    // Clicks below the last line go to the end of the text.
+    assert_eq!(layout.target(0., 500.), Some(Point::new(3, 1)));
+}
+
+#[test]
+fn it_targets_the_end_of_wrapped_rows() {
+    let layout = layout("abc def", Some(40.));
+
+    assert_eq!(layout.target(100., 10.), Some(Point::new(0, 3)));
+    assert_eq!(layout.target(100., 30.), Some(Point::new(0, 7)));
+}
+
+#[test]
+fn it_targets_grapheme_cluster_boundaries() {
+    let layout = layout("e\u{301}x", None);
+
+    assert_eq!(layout.target(4., 10.), Some(Point::new(0, 0)));
+    assert_eq!(layout.target(8., 10.), Some(Point::new(0, 2)));
+    assert_eq!(layout.target(16., 10.), Some(Point::new(0, 3)));
+}
+
+#[test]
+fn it_covers_selections_in_either_direction() {
+    let layout = layout("ab\ncdef\ng", None);
+    let range = Range::new(Point::new(2, 1), Point::new(0, 1));
+
+    // The caret is drawn at the head of a selection made backwards, which is before its anchor.
+    assert_eq!(range.head(), Point::new(0, 1));
+    assert_eq!(range.anchor(), Point::new(2, 1));
+
+    let rects = [
+        [10., 0., 20., 20.],
+        [0., 20., 50., 20.],
+        [0., 40., 10., 20.],
+    ];
+    assert_eq!(layout.rects(range, 0..3), rects);
+    assert_eq!(layout.rects(range.sorted(), 0..3), rects);
+
+    // Only the rows that are asked for are covered.
+    assert_eq!(layout.rects(range, 1..2), [[0., 20., 50., 20.]]);
+    assert!(layout.rects(range, 3..5).is_empty());
+}
+
+#[test]
+fn it_covers_wrapped_rows_with_rects() {
+    let layout = layout("abc def\nxyz", Some(40.));
+    let range = Range::new(Point::new(0, 1), Point::new(1, 2));
+
+    assert_eq!(
+        layout.rects(range, 0..2),
+        [
+            [10., 0., 30., 20.],
+            [0., 20., 40., 20.],
+            [0., 40., 20., 20.]
+        ]
+    );
+    assert_eq!(layout.rects(range, 1..2), [[0., 40., 20., 20.]]);
+}
+
+#[test]
+fn it_measures_visible_lines() {
+    let rope = Rope::from_str(&"abc def\n".repeat(1000));
+    let mut layout = Layout::new(Monospace::with_advance(10.), 20.);
+    layout.set_wrap_width(Some(40.));
+    layout.reset(rope.len_lines());
+
+    // Lines are one row high until they're measured.
+    assert_eq!(layout.height(), 20020.);
+    assert_eq!(layout.pos(Point::new(0, 0)), None);
+
+    assert_eq!(layout.measure_visible(rope.slice(..), 0., 100.), 0..3);
+    assert_eq!(layout.pos(Point::new(2, 4)), Some([0., 100.]));
+    assert!(layout.get(5).is_none());
+
+    // The lines above are still one row high, except for the lines measured before.
+    assert_eq!(
+        layout.measure_visible(rope.slice(..), 10000., 10040.),
+        494..496
+    );
+    assert_eq!(layout.y(494), 9980.);
+    assert!(layout.get(0).is_none());
+}
+
+#[test]
+fn it_updates_edited_lines() {
+    let mut buffer = Buffer::new(
+        language::rust().tree_sitter,
+        "fn a() {}\nfn b() {}\nfn c() {}\n",
+    );
+    let mut layout = Layout::new(Monospace::with_advance(10.), 20.);
+    layout.update(&buffer);
+    layout.measure(buffer.rope.slice(..), 0..4);
+
+    buffer.insert(1, 0, "// b\n");
+    layout.update(&buffer);
+
+    // Only the edited lines are forgotten, and the lines after them are moved down.
+    assert!(layout.get(0).is_some());
+    assert!(layout.get(1).is_none());
+    assert!(layout.get(2).is_none());
+    assert_eq!(layout.get(3).map(|line| line.chars.len()), Some(10));
+    assert_eq!(layout.pos(Point::new(3, 3)), Some([30., 60.]));
+    assert_eq!(layout.height(), 100.);
+
+    buffer.delete(Range::new(Point::new(0, 9), Point::new(2, 0)));
+    layout.update(&buffer);
+    assert!(layout.get(0).is_none());
+    assert_eq!(layout.pos(Point::new(1, 1)), Some([10., 20.]));
+    assert_eq!(layout.height(), 60.);
+}
+
+#[test]
+fn it_moves_line_heights_with_edits() {
+    let text = "a b c\nx\n".repeat(20);
+    let mut buffer = Buffer::new(language::rust().tree_sitter, &text);
+    let mut layout = Layout::new(Monospace::with_advance(10.), 20.);
+    layout.set_wrap_width(Some(40.));
+
+    let mut seed = 7;
+    for _ in 0..40 {
+        seed = (seed * 31 + 17) % 1009;
+        let row = seed % (buffer.rope.len_lines() - 1);
+        if seed % 3 == 0 {
+            let end = (row + seed % 5).min(buffer.rope.len_lines() - 1);
+            buffer.delete(Range::new(Point::new(row, 0), Point::new(end, 0)));
+        } else {
+            buffer.insert(row, 0, &"x\na b c\n".repeat(seed % 4));
+        }
+        layout.update(&buffer);
+        layout.measure(buffer.rope.slice(..), 0..buffer.rope.len_lines());
+
+        // The lines that moved keep their heights, so every line is where a new layout puts it.
+        let expected = self::layout(&buffer.rope.to_string(), Some(40.));
+        for row in 0..=buffer.rope.len_lines() {
+            assert_eq!(layout.y(row), expected.y(row), "{row}");
+        }
+        assert_eq!(layout.height(), expected.height());
+        for y in (0..layout.height() as usize + 20).step_by(10) {
+            assert_eq!(layout.line(y as f64), expected.line(y as f64), "{y}");
+        }
+    }
+}
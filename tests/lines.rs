@@ -0,0 +1,81 @@
+use engrave::{language, Buffer, Highlight, HighlightIndex, Span};
+use std::time::{Duration, Instant};
+use tree_sitter_c2rust::{Query, QueryCursor};
+
+fn highlight(buffer: &Buffer) -> HighlightIndex {
+    let language = language::rust();
+    let query = Query::new(language.tree_sitter, language.highlight_query).unwrap();
+    let text = buffer.rope.to_string();
+    let mut query_cursor = QueryCursor::new();
+    let matches = query_cursor.matches(&query, buffer.tree.root_node(), text.as_bytes());
+    HighlightIndex::new([Highlight::from_matches(&query, matches)])
+}
+
+fn kinds(spans: &[Span]) -> Vec<(Option<&str>, &str)> {
+    spans
+        .iter()
+        .map(|span| (span.kind.as_deref(), &*span.text))
+        .collect()
+}
+
+#[test]
+fn it_highlights_across_rows() {
+    let buffer = Buffer::new(
+        language::rust().tree_sitter,
+        "let a = 1; /* one\ntwo ü\nthree */ let b = 2;\n",
+    );
+    let lines = buffer.lines(0..3, &highlight(&buffer));
+
+    assert_eq!(
+        kinds(&lines[0])[4..],
+        [(None, " "), (Some("comment"), "/* one\n"), (None, "")]
+    );
+    assert_eq!(kinds(&lines[1]), [(Some("comment"), "two ü\n"), (None, "")]);
+    assert_eq!(
+        kinds(&lines[2])[..3],
+        [
+            (Some("comment"), "three */"),
+            (None, " "),
+            (Some("keyword"), "let")
+        ]
+    );
+}
+
+/// Rust source with `items` functions of five lines each, with doc comments, strings and block comments.
+fn generated(items: usize) -> String {
+    (0..items)
+        .map(|n| {
+            format!(
+                "/// Item {n}\nfn f{n}(x: &str) -> usize {{\n    let s = \"{n} ü\"; /* a\n    b */ x.len() + s.len()\n}}\n"
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn it_builds_lines_of_a_large_file() {
+    let buffer = Buffer::new(language::rust().tree_sitter, &generated(2_000));
+    let lines = buffer.lines(0..buffer.rope.len_lines(), &highlight(&buffer));
+
+    assert_eq!(lines.len(), buffer.rope.len_lines());
+    for (row, spans) in lines.iter().enumerate() {
+        let line: String = spans.iter().map(|span| &*span.text).collect();
+        assert_eq!(line, buffer.rope.line(row).to_string());
+    }
+}
+
+/// Run with `cargo test --release -- --ignored` to check the time it takes to build 10k lines.
+#[test]
+#[ignore]
+fn it_builds_lines_of_a_large_file_quickly() {
+    let buffer = Buffer::new(language::rust().tree_sitter, &generated(2_000));
+    let highlights = highlight(&buffer);
+
+    let start = Instant::now();
+    buffer.lines(0..buffer.rope.len_lines(), &highlights);
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed < Duration::from_secs(1),
+        "built 10k lines in {elapsed:?}"
+    );
+}
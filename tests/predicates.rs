@@ -0,0 +1,108 @@
+use engrave::{language, Buffer, Highlight, PredicateArg, Predicates, RopeProvider};
+use tree_sitter_c2rust::{Node, Query, QueryCursor, TextProvider};
+
+fn captures(source: &str, query: &str, predicates: &Predicates) -> Vec<(String, String)> {
+    let language = language::rust();
+    let buffer = Buffer::new(language.tree_sitter, source);
+    let query = Query::new(language.tree_sitter, query).unwrap();
+    let text = buffer.rope.slice(..);
+    let mut query_cursor = QueryCursor::new();
+    let matches = query_cursor
+        .matches(
+            &query,
+            buffer.tree.root_node(),
+            RopeProvider { slice: text },
+        )
+        .filter(|mat| predicates.is_match(&query, mat, text));
+    Highlight::from_matches(&query, matches)
+        .into_iter()
+        .map(|highlight| {
+            let bytes = highlight.range.start_byte..highlight.range.end_byte;
+            (highlight.name, text.byte_slice(bytes).to_string())
+        })
+        .collect()
+}
+
+fn captured<'a>(captures: &'a [(String, String)], name: &str) -> Vec<&'a str> {
+    captures
+        .iter()
+        .filter(|(capture, _)| capture == name)
+        .map(|(_, text)| &**text)
+        .collect()
+}
+
+#[test]
+fn it_provides_node_text_across_chunks() {
+    let source = "fn main() { let identifier = \"a long string\"; }\n".repeat(500);
+    let buffer = Buffer::new(language::rust().tree_sitter, &source);
+    let mut provider = RopeProvider {
+        slice: buffer.rope.slice(..),
+    };
+    assert!(buffer.rope.chunks().count() > 1);
+
+    let mut nodes = vec![buffer.tree.root_node()];
+    while let Some(node) = nodes.pop() {
+        let text: Vec<u8> = provider.text(node).flatten().copied().collect();
+        assert_eq!(text, source[node.byte_range()].as_bytes());
+
+        let mut cursor = node.walk();
+        nodes.extend(node.children(&mut cursor).filter(Node::is_named));
+    }
+}
+
+#[test]
+fn it_matches_highlight_query_predicates() {
+    let captures = captures(
+        "struct Point; fn main() { let point = Some(Point); point.x(); }",
+        language::rust().highlight_query,
+        &Predicates::default(),
+    );
+
+    assert!(captured(&captures, "type").contains(&"Point"));
+    assert!(captured(&captures, "constructor").contains(&"Some"));
+    assert!(!captured(&captures, "constructor").contains(&"point"));
+}
+
+#[test]
+fn it_matches_eq_predicates() {
+    let captures = captures(
+        "fn main() { a; b; }",
+        r#"((identifier) @variable (#eq? @variable "b"))"#,
+        &Predicates::default(),
+    );
+    assert_eq!(captured(&captures, "variable"), ["b"]);
+}
+
+#[test]
+fn it_matches_any_of_predicates() {
+    let source = "fn main() { a; b; c; }";
+    let any_of = captures(
+        source,
+        r#"((identifier) @variable (#any-of? @variable "a" "c"))"#,
+        &Predicates::default(),
+    );
+    assert_eq!(captured(&any_of, "variable"), ["a", "c"]);
+
+    let not_any_of = captures(
+        source,
+        r#"((identifier) @variable (#not-any-of? @variable "a" "c" "main"))"#,
+        &Predicates::default(),
+    );
+    assert_eq!(captured(&not_any_of, "variable"), ["b"]);
+}
+
+#[test]
+fn it_matches_custom_predicates() {
+    let mut predicates = Predicates::empty();
+    predicates.register("is-upper?", |args| match args {
+        [PredicateArg::Capture(text)] => text.chars().all(|c| c.is_ascii_uppercase()),
+        _ => false,
+    });
+
+    let captures = captures(
+        "fn main() { ABC; Abc; abc; }",
+        r#"((identifier) @constant (#is-upper? @constant))"#,
+        &predicates,
+    );
+    assert_eq!(captured(&captures, "constant"), ["ABC"]);
+}
@@ -0,0 +1,194 @@
+use engrave::{
+    search, Buffer, PredicateArg, Predicates, Range, SearchError, SearchMode, SearchQuery,
+};
+use ropey::Rope;
+
+#[test]
+fn it_finds_text_across_chunks() {
+    let text = "fn main() { println!(\"ü {}\", 1); }\n".repeat(500);
+    let rope = Rope::from_str(&text);
+    assert!(rope.chunks().count() > 1);
+
+    // Every occurrence is found where a search of the whole string finds it, even across chunk boundaries.
+    for needle in ["println", "1); }\nfn", "ü", &text[..3000]] {
+        let mut byte_idx = 0;
+        while let Some(expected) = text[byte_idx..].find(needle).map(|idx| idx + byte_idx) {
+            assert_eq!(
+                search::find_in_rope(rope.slice(..), needle, byte_idx),
+                Some(expected)
+            );
+            byte_idx = expected + needle.len();
+        }
+        assert_eq!(search::find_in_rope(rope.slice(..), needle, byte_idx), None);
+    }
+}
+
+fn find(query: &SearchQuery, buffer: &Buffer) -> Vec<String> {
+    query
+        .find(buffer, &Predicates::default())
+        .unwrap()
+        .into_iter()
+        .map(|chars| buffer.rope.slice(chars).to_string())
+        .collect()
+}
+
+/// Replace every match back to front, like replacing all of them at once.
+fn replace_all(query: &SearchQuery, buffer: &mut Buffer, replacement: &str) {
+    let replacements = query
+        .replacements(buffer, &Predicates::default(), replacement)
+        .unwrap();
+    for (chars, text) in replacements.into_iter().rev() {
+        let range = Range::new(
+            buffer.char_to_point(chars.start),
+            buffer.char_to_point(chars.end),
+        );
+        buffer.replace(range, &text);
+    }
+}
+
+#[test]
+fn it_finds_literal_text_ignoring_case() {
+    let buffer = Buffer::new(tree_sitter_rust::language(), "let Foo = foo.FOO();\n");
+
+    assert_eq!(
+        find(&SearchQuery::new("foo"), &buffer),
+        ["Foo", "foo", "FOO"]
+    );
+    assert_eq!(
+        find(&SearchQuery::new("foo").case_sensitive(true), &buffer),
+        ["foo"]
+    );
+    // Literal patterns aren't regular expressions.
+    assert!(find(&SearchQuery::new("f.o"), &buffer).is_empty());
+}
+
+#[test]
+fn it_finds_regular_expressions() {
+    let buffer = Buffer::new(tree_sitter_rust::language(), "let a1 = 1;\nlet ü22 = 22;\n");
+    let query = SearchQuery::new(r"^let (\w+)").mode(SearchMode::Regex);
+    assert_eq!(find(&query, &buffer), ["let a1", "let ü22"]);
+
+    // Matches are character ranges, even after multi-byte characters.
+    let query = SearchQuery::new(r"\d+").mode(SearchMode::Regex);
+    assert_eq!(
+        query.find(&buffer, &Predicates::default()).unwrap(),
+        [5..6, 9..10, 17..19, 22..24]
+    );
+
+    let query = SearchQuery::new(r"1;\nlet").mode(SearchMode::Regex);
+    assert_eq!(find(&query, &buffer), ["1;\nlet"]);
+
+    let query = SearchQuery::new("(").mode(SearchMode::Regex);
+    assert!(matches!(
+        query.find(&buffer, &Predicates::default()),
+        Err(SearchError::Regex(_))
+    ));
+}
+
+#[test]
+fn it_finds_whole_words() {
+    let buffer = Buffer::new(
+        tree_sitter_rust::language(),
+        "foo foobar bar.foo x+ x+y _foo\n",
+    );
+
+    let query = SearchQuery::new("foo").whole_word(true);
+    assert_eq!(
+        query.find(&buffer, &Predicates::default()).unwrap(),
+        [0..3, 15..18]
+    );
+
+    // Ends that aren't word characters don't need a word boundary.
+    let query = SearchQuery::new(".foo").whole_word(true);
+    assert_eq!(find(&query, &buffer), [".foo"]);
+    let query = SearchQuery::new("x+").whole_word(true);
+    assert_eq!(
+        query.find(&buffer, &Predicates::default()).unwrap(),
+        [19..21, 22..24]
+    );
+
+    let query = SearchQuery::new(r"\w+o")
+        .mode(SearchMode::Regex)
+        .whole_word(true);
+    assert_eq!(find(&query, &buffer), ["foo", "foo", "_foo"]);
+}
+
+#[test]
+fn it_replaces_matches() {
+    let mut buffer = Buffer::new(tree_sitter_rust::language(), "let a = b;\nlet c = d;\n");
+
+    let query = SearchQuery::new(r"let (\w) = (\w)").mode(SearchMode::Regex);
+    let replacements = query
+        .replacements(&buffer, &Predicates::default(), "let $2 = $1")
+        .unwrap();
+    assert_eq!(
+        replacements,
+        [
+            (0..9, "let b = a".to_owned()),
+            (11..20, "let d = c".to_owned())
+        ]
+    );
+
+    replace_all(&query, &mut buffer, "let $2 = $1");
+    assert_eq!(buffer.rope.to_string(), "let b = a;\nlet d = c;\n");
+
+    // Literal replacements are inserted as they are.
+    let query = SearchQuery::new("LET").whole_word(true);
+    replace_all(&query, &mut buffer, "$0");
+    assert_eq!(buffer.rope.to_string(), "$0 b = a;\n$0 d = c;\n");
+}
+
+#[test]
+fn it_finds_tree_sitter_query_matches() {
+    let buffer = Buffer::new(tree_sitter_rust::language(), "fn a() { b(1); c(); }\n");
+
+    let query = SearchQuery::new("(call_expression) @call").mode(SearchMode::Query);
+    assert_eq!(find(&query, &buffer), ["b(1)", "c()"]);
+    assert_eq!(
+        query
+            .replacements(&buffer, &Predicates::default(), "x")
+            .unwrap(),
+        [(9..13, "x".to_owned()), (15..18, "x".to_owned())]
+    );
+
+    let query = SearchQuery::new("(not_a_node) @node").mode(SearchMode::Query);
+    assert!(matches!(
+        query.find(&buffer, &Predicates::default()),
+        Err(SearchError::Query(_))
+    ));
+    let query = SearchQuery::new("(identifier)").mode(SearchMode::Query);
+    assert!(matches!(
+        query.find(&buffer, &Predicates::default()),
+        Err(SearchError::NoCaptures)
+    ));
+}
+
+#[test]
+fn it_filters_query_matches_with_predicates() {
+    let buffer = Buffer::new(
+        tree_sitter_rust::language(),
+        "fn a() { self.b(); Self::c(); d(); }\n",
+    );
+    let query = SearchQuery::new(r#"((identifier) @name (#any-of? @name "a" "d"))"#)
+        .mode(SearchMode::Query);
+    assert_eq!(find(&query, &buffer), ["a", "d"]);
+
+    // Custom predicates filter matches too, like they do for highlights.
+    let mut predicates = Predicates::default();
+    predicates.register(
+        "is-short?",
+        |args| matches!(args, [PredicateArg::Capture(text)] if text.len() < 2),
+    );
+    let query = SearchQuery::new("((_) @node (#is-short? @node))").mode(SearchMode::Query);
+    let matches: Vec<_> = query
+        .find(&buffer, &predicates)
+        .unwrap()
+        .into_iter()
+        .map(|chars| buffer.rope.slice(chars).to_string())
+        .collect();
+    assert!(!matches.is_empty());
+    assert!(matches.iter().all(|text| text.len() < 2), "{matches:?}");
+
+    let replacements = query.replacements(&buffer, &predicates, "x").unwrap();
+    assert_eq!(replacements.len(), matches.len());
+}
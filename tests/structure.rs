@@ -0,0 +1,55 @@
+use engrave::{structure, Buffer};
+
+const TEXT: &str = "fn main() { let x = foo(1, 2); }\n";
+
+fn chars(text: &str) -> std::ops::Range<usize> {
+    let start = TEXT.find(text).unwrap();
+    start..start + text.len()
+}
+
+#[test]
+fn it_expands_to_enclosing_nodes() {
+    let buffer = Buffer::new(tree_sitter_rust::language(), TEXT);
+
+    let mut selection = chars("oo").start..chars("oo").start;
+    for expected in [
+        "foo",
+        "foo(1, 2)",
+        "let x = foo(1, 2);",
+        "{ let x = foo(1, 2); }",
+        "fn main() { let x = foo(1, 2); }",
+    ] {
+        selection = structure::expand(&buffer, selection).unwrap();
+        assert_eq!(selection, chars(expected));
+    }
+}
+
+#[test]
+fn it_shrinks_to_the_first_child_node() {
+    let buffer = Buffer::new(tree_sitter_rust::language(), TEXT);
+
+    assert_eq!(
+        structure::shrink(&buffer, chars("foo(1, 2)")),
+        Some(chars("foo"))
+    );
+    assert_eq!(
+        structure::shrink(&buffer, chars("(1, 2)")),
+        Some(chars("1"))
+    );
+    assert_eq!(structure::shrink(&buffer, chars("foo")), None);
+}
+
+#[test]
+fn it_selects_sibling_nodes() {
+    let buffer = Buffer::new(tree_sitter_rust::language(), TEXT);
+
+    assert_eq!(
+        structure::sibling(&buffer, chars("1"), true),
+        Some(chars("2"))
+    );
+    assert_eq!(
+        structure::sibling(&buffer, chars("2"), false),
+        Some(chars("1"))
+    );
+    assert_eq!(structure::sibling(&buffer, chars("2"), true), None);
+}
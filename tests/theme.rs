@@ -0,0 +1,76 @@
+use engrave::{Error, Style, Theme};
+
+#[test]
+fn it_loads_vs_code_themes_with_comments_and_trailing_commas() {
+    let json = r##"{
+        // Line comments are removed, but not inside strings.
+        "name": "Test // not a comment",
+        "type": "dark",
+        /* Block
+           comments too, */
+        "colors": {
+            "editor.background": "#101010",
+            "editor.lineHighlightBackground": "#202020",
+            "editor.lineHighlightBorder": "#303030",
+            "editorWidget.background": "#404040",
+            "inputValidation.errorForeground": "#ff0000",
+        },
+        "tokenColors": [
+            {
+                "scope": ["keyword", "source.rust storage.type"],
+                "settings": { "foreground": "#ff7b72", "fontStyle": "bold italic", },
+            },
+            {
+                "scope": "entity.name.function",
+                "settings": { "foreground": "#d2a8ff" },
+            },
+        ],
+    }"##;
+    let theme = Theme::from_json(json).unwrap();
+
+    assert_eq!(theme.name, "Test // not a comment");
+    assert_eq!(theme.background, "#101010");
+    assert_eq!(theme.panel, "#404040");
+    assert_eq!(theme.error, "#ff0000");
+    // The line highlight background isn't a border, so only the border is used.
+    assert_eq!(theme.current_line, "#303030");
+    // Colors the theme doesn't set come from the dark theme.
+    assert_eq!(theme.cursor, Theme::dark().cursor);
+
+    let keyword = Style::new("#ff7b72").bold(true).italic(true);
+    assert_eq!(theme.style("keyword"), Some(&keyword));
+    assert_eq!(theme.style("storage.type"), Some(&keyword));
+    // Capture names fall back to the TextMate scope they correspond to.
+    assert_eq!(theme.style("function.method"), Some(&Style::new("#d2a8ff")));
+    assert_eq!(theme.style("string"), None);
+}
+
+#[test]
+fn it_loads_textmate_themes() {
+    let json = r##"{
+        "name": "TextMate",
+        "settings": [
+            { "settings": { "background": "#fafafa", "caret": "#111", "lineHighlight": "#eee" } },
+            { "scope": "comment", "settings": { "foreground": "#999", "fontStyle": "italic" } }
+        ]
+    }"##;
+    let theme = Theme::from_json(json).unwrap();
+
+    assert_eq!(theme.background, "#fafafa");
+    assert_eq!(theme.cursor, "#111");
+    assert_eq!(theme.current_line, Theme::light().current_line);
+    assert_eq!(
+        theme.style("comment"),
+        Some(&Style::new("#999").italic(true))
+    );
+}
+
+#[test]
+fn it_rejects_invalid_themes() {
+    assert!(matches!(Theme::from_json("{"), Err(Error::Theme(_))));
+    assert!(matches!(Theme::from_json("[]"), Err(Error::Theme(_))));
+    assert_eq!(
+        Theme::from_json(r#"{ "name": "Empty" }"#),
+        Err(Error::Theme("missing token colors".to_owned()))
+    );
+}
@@ -0,0 +1,139 @@
+use engrave::{Buffer, VimAction, VimParse};
+
+fn action(input: &str) -> VimAction {
+    match VimAction::parse(input, false) {
+        VimParse::Done(action) => action,
+        VimParse::Incomplete => panic!("`{input}` is incomplete"),
+        VimParse::Invalid => panic!("`{input}` is invalid"),
+    }
+}
+
+/// Text that the operator typed as `input` applies to, with the cursor at `idx`.
+fn operator_text(text: &str, idx: usize, input: &str) -> Option<String> {
+    let buffer = Buffer::new(tree_sitter_rust::language(), text);
+    let chars = action(input).range(&buffer, idx)?;
+    Some(buffer.rope.slice(chars).to_string())
+}
+
+/// Where the motion typed as `input` moves the cursor at `idx`.
+fn target(text: &str, idx: usize, input: &str) -> usize {
+    let buffer = Buffer::new(tree_sitter_rust::language(), text);
+    action(input).target(&buffer, idx).unwrap()
+}
+
+#[test]
+fn it_parses_registers_counts_and_operators() {
+    let delete = action("\"a3dw");
+    assert_eq!((delete.register, delete.count), (Some('a'), Some(3)));
+    assert_eq!(
+        operator_text("a b c d", 0, "\"a3dw").as_deref(),
+        Some("a b c ")
+    );
+
+    assert_eq!(action("2d3w").count, Some(6));
+    assert_eq!(operator_text("f(a, b)", 3, "ci(").as_deref(), Some("a, b"));
+    assert_eq!(target("a\nb\nc", 4, "gg"), 0);
+    assert_eq!(target("  ab", 3, "0"), 0);
+    assert_eq!(action("10j").count, Some(10));
+}
+
+#[test]
+fn it_moves_to_the_first_non_blank_character() {
+    assert_eq!(target("    foo", 6, "^"), 4);
+    assert_eq!(target("a\n\t bar\n", 7, "^"), 4);
+    assert_eq!(operator_text("  foo", 4, "d^").as_deref(), Some("fo"));
+}
+
+#[test]
+fn it_parses_incomplete_and_invalid_input() {
+    for input in ["", "\"", "3", "d", "\"ad", "g", "ci", "d2"] {
+        assert_eq!(
+            VimAction::parse(input, false),
+            VimParse::Incomplete,
+            "{input}"
+        );
+    }
+    for input in ["dz", "gz", "\"!", "Z"] {
+        assert_eq!(VimAction::parse(input, false), VimParse::Invalid, "{input}");
+    }
+}
+
+#[test]
+fn it_parses_visual_operators() {
+    let buffer = Buffer::new(tree_sitter_rust::language(), "abc");
+    for input in ["d", "x"] {
+        let VimParse::Done(action) = VimAction::parse(input, true) else {
+            panic!("`{input}` isn't done");
+        };
+        // Visual operators apply to the selection instead of a motion.
+        assert_eq!(action.range(&buffer, 0), None, "{input}");
+    }
+}
+
+#[test]
+fn it_repeats_changes_with_dot() {
+    assert_eq!(action("3.").count, Some(3));
+
+    // Only changes are recorded for `.` to repeat.
+    for input in ["x", "dw", "cw", "dd", "D", "p", "o", "A"] {
+        assert!(action(input).is_change(), "{input}");
+    }
+    for input in ["yw", "yy", "w", "$", "u", "."] {
+        assert!(!action(input).is_change(), "{input}");
+    }
+}
+
+#[test]
+fn it_moves_to_the_end_of_empty_and_last_lines() {
+    assert_eq!(target("a\n\nb", 2, "$"), 2);
+    assert_eq!(target("abc\ndef", 1, "$"), 2);
+    assert_eq!(target("ab\ncd", 3, "$"), 4);
+    assert_eq!(target("ab\ncd\n", 3, "$"), 4);
+}
+
+#[test]
+fn it_deletes_to_the_end_of_lines() {
+    assert_eq!(operator_text("a\n\nb", 2, "D"), None);
+    assert_eq!(operator_text("a\n\nb", 2, "C"), None);
+    assert_eq!(operator_text("abc\ndef", 1, "D").as_deref(), Some("bc"));
+    assert_eq!(operator_text("ab\ncd", 3, "d$").as_deref(), Some("cd"));
+    assert_eq!(operator_text("ab\r\ncd", 0, "D").as_deref(), Some("ab"));
+    assert_eq!(
+        operator_text("ab\ncd\nef", 1, "2D").as_deref(),
+        Some("b\ncd")
+    );
+    assert_eq!(operator_text("ab\n", 3, "D"), None);
+}
+
+#[test]
+fn it_changes_words() {
+    assert_eq!(operator_text("foo bar", 0, "cw").as_deref(), Some("foo"));
+    assert_eq!(operator_text("foo bar", 2, "cw").as_deref(), Some("o"));
+    assert_eq!(
+        operator_text("foo bar baz", 0, "2cw").as_deref(),
+        Some("foo bar")
+    );
+    assert_eq!(operator_text("foo   bar", 3, "cw").as_deref(), Some("   "));
+    assert_eq!(operator_text("foo bar", 0, "dw").as_deref(), Some("foo "));
+    assert_eq!(operator_text("foo\nbar", 0, "dw").as_deref(), Some("foo"));
+}
+
+#[test]
+fn it_applies_counts() {
+    assert_eq!(operator_text("abcdef", 0, "3x").as_deref(), Some("abc"));
+    assert_eq!(operator_text("abc", 1, "5x").as_deref(), Some("bc"));
+    assert_eq!(operator_text("a b c d", 0, "2dw").as_deref(), Some("a b "));
+    assert_eq!(operator_text("a b c d", 0, "d2w").as_deref(), Some("a b "));
+    assert_eq!(
+        operator_text("a\nb\nc\nd", 0, "2dj").as_deref(),
+        Some("a\nb\nc\n")
+    );
+}
+
+#[test]
+fn it_deletes_lines() {
+    assert_eq!(operator_text("a\nb\nc", 2, "dd").as_deref(), Some("b\n"));
+    assert_eq!(operator_text("a\nb\nc", 2, "2dd").as_deref(), Some("b\nc"));
+    assert_eq!(operator_text("a\nb\nc", 4, "5dd").as_deref(), Some("c"));
+    assert_eq!(operator_text("a\nb\n", 0, "yy").as_deref(), Some("a\n"));
+}